@@ -1,13 +1,38 @@
 use axum::{
-    extract::State,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
     Json,
 };
+use serde::Deserialize;
 use std::sync::Arc;
-use crate::models::{CheckResult, StatsResponse, CategoryStats, ServerStats, Status};
+use crate::config::MaintenanceScope;
+use crate::models::{CheckResult, StatsResponse, CategoryStats, ServerStats, Silence, Status};
 use crate::engine::Monitor;
 use std::collections::HashMap;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(serde_json::json!({ "error": message.into() })))
+}
+
+/// State-changing endpoints stay closed until an `api_token` is configured.
+async fn authorize(monitor: &Monitor, headers: &HeaderMap) -> Result<(), ApiError> {
+    let cfg = monitor.config.read().await;
+    let Some(token) = &cfg.api_token else {
+        return Err(api_error(StatusCode::FORBIDDEN, "Set api_token in the config to enable this endpoint"));
+    };
+    let presented = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if presented != Some(token.as_str()) {
+        return Err(api_error(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"));
+    }
+    Ok(())
+}
+
+#[allow(clippy::unwrap_or_default)]
 pub async fn get_stats(
     State(monitor): State<Arc<Monitor>>
 ) -> Json<StatsResponse> {
@@ -30,7 +55,8 @@ pub async fn get_stats(
     let mut cat_map: HashMap<String, HashMap<String, Vec<CheckResult>>> = HashMap::new();
     let mut server_addresses: HashMap<String, String> = HashMap::new();
 
-    for mut res in results {
+    // Per-location results are shown through the `locations` of their combined result.
+    for mut res in results.into_iter().filter(|r| r.location.is_none()) {
         if hide {
             res.mask_addresses();
         }
@@ -72,3 +98,67 @@ pub async fn get_stats(
     })
 }
 
+
+#[derive(Debug, Deserialize)]
+pub struct SilenceRequest {
+    #[serde(flatten)]
+    pub scope: MaintenanceScope,
+    /// Seconds from now; alternative to `expires_at`.
+    #[serde(default)]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+pub async fn list_silences(
+    State(monitor): State<Arc<Monitor>>
+) -> Json<Vec<Silence>> {
+    Json(monitor.active_silences().await)
+}
+
+pub async fn create_silence(
+    State(monitor): State<Arc<Monitor>>,
+    headers: HeaderMap,
+    Json(request): Json<SilenceRequest>,
+) -> Result<(StatusCode, Json<Silence>), ApiError> {
+    authorize(&monitor, &headers).await?;
+    if request.scope.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Scope the silence to a category, server, check or label"));
+    }
+    let now = Utc::now();
+    let expires_at = match (request.expires_at, request.duration) {
+        (Some(at), None) => at,
+        (None, Some(secs)) => now + chrono::Duration::seconds(secs.min(i64::MAX as u64) as i64),
+        _ => return Err(api_error(StatusCode::BAD_REQUEST, "Set exactly one of duration or expires_at")),
+    };
+    if expires_at <= now {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Silence would already be expired"));
+    }
+
+    let silence = Silence {
+        id: uuid::Uuid::new_v4().to_string(),
+        scope: request.scope,
+        reason: request.reason,
+        created_at: now,
+        expires_at,
+    };
+    monitor.add_silence(silence.clone()).await
+        .map_err(|e| api_error(StatusCode::SERVICE_UNAVAILABLE, format!("Failed to store silence: {}", e)))?;
+    tracing::info!("[MAINTENANCE] {} created until {}", silence.describe(), silence.expires_at);
+    Ok((StatusCode::CREATED, Json(silence)))
+}
+
+pub async fn delete_silence(
+    State(monitor): State<Arc<Monitor>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    authorize(&monitor, &headers).await?;
+    match monitor.remove_silence(&id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(api_error(StatusCode::NOT_FOUND, "No such silence")),
+        Err(e) => Err(api_error(StatusCode::SERVICE_UNAVAILABLE, format!("Failed to delete silence: {}", e))),
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    routing::get,
+    routing::{delete, get},
     Router,
 };
 use std::sync::Arc;
@@ -14,6 +14,8 @@ pub mod handlers;
 pub fn create_router(monitor: Arc<Monitor>) -> Router {
     Router::new()
         .route("/api/stats", get(handlers::get_stats))
+        .route("/api/silences", get(handlers::list_silences).post(handlers::create_silence))
+        .route("/api/silences/{id}", delete(handlers::delete_silence))
         .fallback_service(ServeDir::new("public"))
         .with_state(monitor)
 }
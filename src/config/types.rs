@@ -1,4 +1,9 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use sha2::Digest;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MonitorConfig {
@@ -20,21 +25,427 @@ pub struct MonitorConfig {
     pub redis_prefix: String,
     #[serde(default = "default_max_checks_per_second")]
     pub max_checks_per_second: u64,
+    /// Cap on checks started against any one target host; unset means no per-host cap.
+    #[serde(default)]
+    pub max_checks_per_second_per_host: Option<f64>,
     #[serde(default)]
     pub enable_dashboard: Option<bool>,
+    #[serde(default)]
+    pub traceroute: TracerouteConfig,
+    /// Default proxy for Http and TcpPort checks that do not set their own.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Planned windows during which matching checks are flagged instead of alerting.
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// Bearer token required by API endpoints that change state; they are disabled without it.
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Consecutive failing results before a check is alerted as failing.
+    #[serde(default = "default_streak")]
+    pub fail_after: u32,
+    /// Consecutive Up results before a recovery is alerted.
+    #[serde(default = "default_streak")]
+    pub recover_after: u32,
+    #[serde(default)]
+    pub flap_detection: Option<FlapConfig>,
+    /// Other live nodes asked to re-check a failure before it counts as Down; 0 disables it.
+    #[serde(default)]
+    pub confirm_from: usize,
+    #[serde(default = "default_confirm_timeout")]
+    pub confirm_timeout_ms: u64,
+    /// Peer replies needed before quorum can rule on a failure.
+    #[serde(default = "default_confirm_min_replies")]
+    pub confirm_min_replies: usize,
+    /// How per-location results of `all_nodes` checks combine into one status.
+    #[serde(default)]
+    pub status_policy: StatusPolicy,
+    /// Default retry policy; servers and checks can set their own.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Seconds between checks of a target that is failing, so recovery is seen sooner.
+    #[serde(default)]
+    pub interval_when_down: Option<u64>,
+    #[serde(default)]
+    pub interval_when_degraded: Option<u64>,
+}
+
+impl MonitorConfig {
+    pub fn servers(&self) -> impl Iterator<Item = &Server> {
+        self.categories.iter().flat_map(|c| c.servers.iter())
+    }
+
+    /// Rejects `depends_on` entries that name unknown servers or form a cycle.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for server in self.servers() {
+            parents.entry(&server.name).or_default().extend(server.depends_on.iter().map(String::as_str));
+        }
+        for (server, deps) in &parents {
+            if let Some(unknown) = deps.iter().find(|d| !parents.contains_key(*d)) {
+                return Err(format!("Server '{}' depends on unknown server '{}'", server, unknown));
+            }
+        }
+
+        // Depth-first search; a node seen again while still on the stack closes a cycle.
+        fn visit<'a>(node: &'a str, parents: &HashMap<&'a str, Vec<&'a str>>, stack: &mut Vec<&'a str>, done: &mut HashSet<&'a str>) -> Result<(), String> {
+            if done.contains(node) {
+                return Ok(());
+            }
+            if let Some(pos) = stack.iter().position(|n| *n == node) {
+                let mut cycle = stack[pos..].to_vec();
+                cycle.push(node);
+                return Err(format!("Dependency cycle: {}", cycle.join(" -> ")));
+            }
+            stack.push(node);
+            for parent in parents.get(node).into_iter().flatten() {
+                visit(parent, parents, stack, done)?;
+            }
+            stack.pop();
+            done.insert(node);
+            Ok(())
+        }
+
+        let mut names: Vec<&str> = parents.keys().copied().collect();
+        names.sort();
+        let mut done = HashSet::new();
+        for name in names {
+            visit(name, &parents, &mut Vec::new(), &mut done)?;
+        }
+
+        if let Some(flap) = &self.flap_detection
+            && (flap.window < 2 || flap.low_pct > flap.high_pct)
+        {
+            return Err("flap_detection needs a window of at least 2 and low_pct <= high_pct".into());
+        }
+        for window in &self.maintenance {
+            window.validate().map_err(|e| format!("Maintenance window '{}': {}", window.name, e))?;
+        }
+        if self.redis_url.is_some() {
+            // Every node would run an unpinned host check and overwrite the others' results.
+            if let Some(server) = self.servers().find(|s| s.node.is_none() && s.checks.iter().any(|c| c.is_local())) {
+                return Err(format!("Server '{}' has host checks but no \"node\" to pin them to", server.name));
+            }
+        }
+        if self.check_interval == 0 {
+            return Err("check_interval must be positive".into());
+        }
+        let fast = [self.interval_when_down, self.interval_when_degraded].into_iter()
+            .chain(self.servers().flat_map(|s| [s.interval_when_down, s.interval_when_degraded]));
+        if fast.flatten().any(|i| i == 0) {
+            return Err("interval_when_down and interval_when_degraded must be positive".into());
+        }
+        let host_caps = std::iter::once(self.max_checks_per_second_per_host)
+            .chain(self.servers().map(|s| s.max_checks_per_second_per_host));
+        if host_caps.flatten().any(|r| !(r > 0.0 && r.is_finite())) {
+            return Err("max_checks_per_second_per_host must be a positive number".into());
+        }
+        let min_replies = std::iter::once(Some(self.confirm_min_replies))
+            .chain(self.servers().map(|s| s.confirm_min_replies));
+        if min_replies.flatten().any(|n| n == 0) {
+            return Err("confirm_min_replies must be at least 1".into());
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate().map_err(|e| format!("retry: {}", e))?;
+        }
+        for server in self.servers() {
+            for check in &server.checks {
+                if let CheckType::Snmp { oids, .. } = check {
+                    for spec in oids {
+                        parse_oid(&spec.oid).map_err(|e| format!("Server '{}': {}", server.name, e))?;
+                    }
+                }
+            }
+            if server.source_binding().is_unbound() {
+                continue;
+            }
+            if let Some(check) = server.checks.iter().find(|c| !c.supports_binding()) {
+                return Err(format!("Server '{}' sets source_address/interface, but its {} check cannot be bound", server.name, check.base_name()));
+            }
+        }
+        for server in self.servers() {
+            let policies = server.retry.iter().chain(server.checks.iter().filter_map(|c| c.retry()));
+            for retry in policies {
+                retry.validate().map_err(|e| format!("Server '{}' retry: {}", server.name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Length of each server's longest dependency chain, so parents can be evaluated before children.
+    pub fn dependency_depths(&self) -> HashMap<String, usize> {
+        fn depth(name: &str, cfg: &MonitorConfig, memo: &mut HashMap<String, usize>) -> usize {
+            if let Some(d) = memo.get(name) {
+                return *d;
+            }
+            memo.insert(name.to_string(), 0); // guards against cycles in unvalidated configs
+            let parents: Vec<String> = cfg.servers().filter(|s| s.name == name).flat_map(|s| s.depends_on.clone()).collect();
+            let d = parents.iter().map(|p| depth(p, cfg, memo) + 1).max().unwrap_or(0);
+            memo.insert(name.to_string(), d);
+            d
+        }
+        let mut memo = HashMap::new();
+        for server in self.servers() {
+            depth(&server.name, self, &mut memo);
+        }
+        memo
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyConfig {
+    /// `http://host:port` for CONNECT, `socks5://host:port`, or `socks5h://` to resolve names at the proxy.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Hosts reached directly: `*`, exact names, `.domain` suffixes, IPs or CIDR ranges.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
 }
 
+impl ProxyConfig {
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+        let ip = host.parse::<IpAddr>().ok();
+        self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            if entry == "*" {
+                return true;
+            }
+            if let (Some(ip), Ok(net)) = (ip, entry.parse::<ipnet::IpNet>()) {
+                return net.contains(&ip);
+            }
+            let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+            host == domain || host.ends_with(&format!(".{}", domain))
+        })
+    }
+}
+
+/// Nagios-style flap detection over a check's recent results.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlapConfig {
+    /// Number of recent results considered.
+    #[serde(default = "default_flap_window")]
+    pub window: usize,
+    /// Share of state changes in the window, in percent, at which a check starts flapping.
+    #[serde(default = "default_flap_high_pct")]
+    pub high_pct: f64,
+    /// Share of state changes below which a flapping check is considered stable again.
+    #[serde(default = "default_flap_low_pct")]
+    pub low_pct: f64,
+}
+
+/// How failed checks are retried. Unset `attempts` falls back to the server's `max_retries` + 1.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RetryPolicy {
+    /// Total tries, the first one included.
+    #[serde(default)]
+    pub attempts: Option<u32>,
+    #[serde(default = "default_retry_initial_delay")]
+    pub initial_delay_ms: u64,
+    /// Factor applied to the delay after each retry.
+    #[serde(default = "default_retry_multiplier")]
+    pub multiplier: f64,
+    #[serde(default = "default_retry_max_delay")]
+    pub max_delay_ms: u64,
+    /// Random spread applied to each delay as a fraction, e.g. `0.2` for +/-20%.
+    #[serde(default)]
+    pub jitter: f64,
+    /// Cap on the whole check including retries; defaults to the check interval.
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.attempts == Some(0) {
+            return Err("attempts must be at least 1".into());
+        }
+        if !(self.multiplier >= 1.0 && self.multiplier.is_finite()) {
+            return Err("multiplier must be at least 1".into());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("jitter must be between 0 and 1".into());
+        }
+        if self.deadline_ms == Some(0) {
+            return Err("deadline_ms must be positive".into());
+        }
+        Ok(())
+    }
+
+    /// What applies when no policy is set anywhere: tries a fixed 50ms apart.
+    pub fn fixed_gap() -> Self {
+        let gap = default_retry_initial_delay();
+        RetryPolicy { initial_delay_ms: gap, multiplier: 1.0, max_delay_ms: gap, ..Default::default() }
+    }
+
+    /// Delay before the `retry`-th retry (1-based), without jitter.
+    pub fn backoff(&self, retry: u32) -> std::time::Duration {
+        let ms = self.initial_delay_ms as f64 * self.multiplier.powi(retry.saturating_sub(1) as i32);
+        std::time::Duration::from_millis(ms.min(self.max_delay_ms as f64) as u64)
+    }
+}
+
+pub fn default_retry_initial_delay() -> u64 { 50 }
+pub fn default_retry_multiplier() -> f64 { 2.0 }
+pub fn default_retry_max_delay() -> u64 { 10_000 }
+
+pub fn default_streak() -> u32 { 1 }
+pub fn default_flap_window() -> usize { 21 }
+pub fn default_flap_high_pct() -> f64 { 30.0 }
+pub fn default_flap_low_pct() -> f64 { 15.0 }
+
+/// Which results a maintenance window or silence covers. Unset fields match everything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MaintenanceScope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Check name as shown in results, e.g. `TCP:443`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<String>,
+    /// One of the server's `labels`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl MaintenanceScope {
+    pub fn is_empty(&self) -> bool {
+        *self == MaintenanceScope::default()
+    }
+
+    pub fn matches(&self, category: &str, server: &str, check: &str, labels: &[String]) -> bool {
+        self.category.as_ref().is_none_or(|c| c == category)
+            && self.server.as_ref().is_none_or(|s| s == server)
+            && self.check.as_ref().is_none_or(|c| c == check)
+            && self.label.as_ref().is_none_or(|l| labels.contains(l))
+    }
+}
+
+/// A one-off window (`start`..`end`) or a recurring one opening at each `cron` match for `duration` seconds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    #[serde(flatten)]
+    pub scope: MaintenanceScope,
+    #[serde(default)]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+    /// Five-field cron expression evaluated in UTC, e.g. `0 2 * * SUN`.
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+impl MaintenanceWindow {
+    fn schedule(&self) -> Result<Option<croner::Cron>, String> {
+        self.cron.as_deref()
+            .map(|expr| croner::Cron::new(expr).parse().map_err(|e| format!("invalid cron '{}': {}", expr, e)))
+            .transpose()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.schedule()?, self.duration) {
+            (Some(_), Some(d)) if d > 0 => Ok(()),
+            (Some(_), _) => Err("cron windows need a positive duration".into()),
+            (None, _) => match (self.start, self.end) {
+                (Some(start), Some(end)) if start < end => Ok(()),
+                (Some(_), Some(_)) => Err("end must be after start".into()),
+                _ => Err("set start and end, or cron and duration".into()),
+            },
+        }
+    }
+
+    /// For recurring windows, `start` and `end` only bound the period in which the schedule applies.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if self.start.is_some_and(|s| now < s) || self.end.is_some_and(|e| now >= e) {
+            return false;
+        }
+        let Ok(schedule) = self.schedule() else { return false };
+        let Some(schedule) = schedule else { return self.start.is_some() && self.end.is_some() };
+        let duration = ChronoDuration::seconds(self.duration.unwrap_or(0) as i64);
+        schedule.find_next_occurrence(&(now - duration), true)
+            .is_ok_and(|opened| opened <= now && now < opened + duration)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TracerouteConfig {
+    /// Trace the path when a Ping or TCP check transitions to Down.
+    #[serde(default)]
+    pub on_down: bool,
+    #[serde(default = "default_trace_max_hops")]
+    pub max_hops: u8,
+    #[serde(default = "default_trace_probes")]
+    pub probes: u32,
+    #[serde(default = "default_trace_timeout")]
+    pub timeout_ms: u64,
+    /// Seconds between MTR runs for servers with `mtr` enabled.
+    #[serde(default = "default_mtr_interval")]
+    pub mtr_interval: u64,
+    #[serde(default = "default_mtr_probes")]
+    pub mtr_probes: u32,
+}
+
+impl Default for TracerouteConfig {
+    fn default() -> Self {
+        Self {
+            on_down: false,
+            max_hops: default_trace_max_hops(),
+            probes: default_trace_probes(),
+            timeout_ms: default_trace_timeout(),
+            mtr_interval: default_mtr_interval(),
+            mtr_probes: default_mtr_probes(),
+        }
+    }
+}
+
+pub fn default_trace_max_hops() -> u8 { 30 }
+pub fn default_trace_probes() -> u32 { 3 }
+pub fn default_trace_timeout() -> u64 { 1000 }
+pub fn default_mtr_interval() -> u64 { 300 }
+pub fn default_mtr_probes() -> u32 { 10 }
+
 
 
 
 pub fn default_packet_loss_threshold() -> f64 { 101.0 }
 pub fn default_redis_prefix() -> String { "spectra".into() }
 pub fn default_max_checks_per_second() -> u64 { 50 }
+pub fn default_confirm_timeout() -> u64 { 5000 }
+pub fn default_confirm_min_replies() -> usize { 1 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Category {
     pub name: String,
     pub servers: Vec<Server>,
+    #[serde(default)]
+    pub mode: Option<CheckMode>,
+}
+
+/// Whether a check runs on one node of the mesh or on every node.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckMode {
+    #[default]
+    Shard,
+    /// Each node checks from its own location and stores a node-qualified result.
+    AllNodes,
+}
+
+/// Which share of locations must see a target operational for it to count as Up.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPolicy {
+    Any,
+    All,
+    #[default]
+    Majority,
 }
 
 pub fn default_api_port() -> u16 { 3000 }
@@ -49,6 +460,102 @@ pub struct Server {
     pub max_retries: u32,
     #[serde(default)]
     pub packet_loss_threshold: Option<f64>,
+    #[serde(default)]
+    pub node: Option<String>,
+    /// Periodically collect MTR-style per-hop loss for Ping and TCP checks.
+    #[serde(default)]
+    pub mtr: bool,
+    #[serde(default)]
+    pub source_address: Option<IpAddr>,
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Servers this one is reached through; its failures are suppressed while any of them is down.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Free-form tags that maintenance windows and silences can target.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub fail_after: Option<u32>,
+    #[serde(default)]
+    pub recover_after: Option<u32>,
+    #[serde(default)]
+    pub confirm_from: Option<usize>,
+    #[serde(default)]
+    pub confirm_min_replies: Option<usize>,
+    /// Overrides the category's `mode`.
+    #[serde(default)]
+    pub mode: Option<CheckMode>,
+    #[serde(default)]
+    pub status_policy: Option<StatusPolicy>,
+    /// Node labels a node must carry to be given this server's checks.
+    #[serde(default)]
+    pub run_on: BTreeMap<String, String>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default)]
+    pub interval_when_down: Option<u64>,
+    #[serde(default)]
+    pub interval_when_degraded: Option<u64>,
+    /// Overrides the global per-host cap for this server's targets.
+    #[serde(default)]
+    pub max_checks_per_second_per_host: Option<f64>,
+}
+
+impl Server {
+    pub fn check_mode(&self, category: &Category) -> CheckMode {
+        self.mode.or(category.mode).unwrap_or_default()
+    }
+
+    /// The server's `run_on` labels, with the check's own taking precedence.
+    pub fn selector(&self, check: &CheckType) -> BTreeMap<String, String> {
+        let mut selector = self.run_on.clone();
+        if let Some(own) = check.run_on() {
+            selector.extend(own.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        selector
+    }
+
+    pub fn source_binding(&self) -> SourceBinding {
+        SourceBinding { address: self.source_address, interface: self.interface.clone() }
+    }
+
+    /// The check's retry policy, else the server's, else the global one, else a fixed 50ms gap.
+    pub fn retry_policy(&self, check: &CheckType, global: Option<&RetryPolicy>) -> RetryPolicy {
+        check.retry().or(self.retry.as_ref()).or(global).cloned().unwrap_or_else(RetryPolicy::fixed_gap)
+    }
+}
+
+/// Local address and/or network device that outgoing probes leave from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SourceBinding {
+    pub address: Option<IpAddr>,
+    pub interface: Option<String>,
+}
+
+impl SourceBinding {
+    pub fn is_unbound(&self) -> bool {
+        self.address.is_none() && self.interface.is_none()
+    }
+
+    /// Fills unset fields from a broader binding, e.g. the check's from its server's.
+    pub fn or(self, fallback: SourceBinding) -> SourceBinding {
+        SourceBinding {
+            address: self.address.or(fallback.address),
+            interface: self.interface.or(fallback.interface),
+        }
+    }
+}
+
+impl fmt::Display for SourceBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.interface, &self.address) {
+            (Some(iface), Some(addr)) => write!(f, "{}/{}", iface, addr),
+            (Some(iface), None) => write!(f, "{}", iface),
+            (None, Some(addr)) => write!(f, "{}", addr),
+            (None, None) => write!(f, "default"),
+        }
+    }
 }
 
 
@@ -65,6 +572,14 @@ pub enum CheckType {
         timeout_ms: u64,
         #[serde(default)]
         simulate_loss: Option<f64>,
+        #[serde(default)]
+        source_address: Option<IpAddr>,
+        #[serde(default)]
+        interface: Option<String>,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
     },
     TcpPort {
         port: u16,
@@ -74,6 +589,16 @@ pub enum CheckType {
         timeout_ms: u64,
         #[serde(default)]
         simulate_loss: Option<f64>,
+        #[serde(default)]
+        source_address: Option<IpAddr>,
+        #[serde(default)]
+        interface: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
     },
     UdpPort {
         port: u16,
@@ -83,6 +608,14 @@ pub enum CheckType {
         timeout_ms: u64,
         #[serde(default)]
         simulate_loss: Option<f64>,
+        #[serde(default)]
+        source_address: Option<IpAddr>,
+        #[serde(default)]
+        interface: Option<String>,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
     },
     Http {
         #[serde(default = "default_http_method")]
@@ -92,12 +625,649 @@ pub enum CheckType {
         contains: Option<String>,
         #[serde(default = "default_http_timeout")]
         timeout_ms: Option<u64>,
+        #[serde(default)]
+        source_address: Option<IpAddr>,
+        #[serde(default)]
+        interface: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Disk {
+        #[serde(default = "default_disk_mount")]
+        mount: String,
+        #[serde(default = "default_disk_warn_pct")]
+        warn_pct: f64,
+        #[serde(default = "default_disk_crit_pct")]
+        crit_pct: f64,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Memory {
+        #[serde(default = "default_memory_warn_pct")]
+        warn_pct: f64,
+        #[serde(default = "default_memory_crit_pct")]
+        crit_pct: f64,
+        #[serde(default)]
+        swap_warn_pct: Option<f64>,
+        #[serde(default)]
+        swap_crit_pct: Option<f64>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Load {
+        #[serde(default = "default_load_warn")]
+        warn: f64,
+        #[serde(default = "default_load_crit")]
+        crit: f64,
+        #[serde(default = "default_true")]
+        per_core: bool,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Process {
+        name: String,
+        #[serde(default = "default_process_min")]
+        warn_below: u32,
+        #[serde(default = "default_process_min")]
+        crit_below: u32,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    ListeningPort {
+        port: u16,
+        #[serde(default)]
+        warn_connections: Option<u32>,
+        #[serde(default)]
+        crit_connections: Option<u32>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    FileAge {
+        path_glob: String,
+        /// Seconds the newest matching file may age before the check fails.
+        max_age: u64,
+        #[serde(default)]
+        min_size: Option<u64>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    LogMatch {
+        path: String,
+        regex: String,
+        /// Seconds of history the match count covers.
+        #[serde(default = "default_log_window")]
+        window: u64,
+        /// Matches within the window that mark the check `Down`.
+        #[serde(default = "default_log_threshold")]
+        threshold: u64,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Command {
+        argv: Vec<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        env: HashMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Postgres {
+        #[serde(default = "default_postgres_port")]
+        port: u16,
+        #[serde(default = "default_postgres_user")]
+        user: String,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        database: Option<String>,
+        #[serde(default)]
+        query: Option<String>,
+        #[serde(default)]
+        role: Option<String>,
+        #[serde(default)]
+        tls: PostgresTls,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    MySql {
+        #[serde(default = "default_mysql_port")]
+        port: u16,
+        #[serde(default = "default_mysql_user")]
+        user: String,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        database: Option<String>,
+        #[serde(default)]
+        query: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Redis {
+        #[serde(default = "default_redis_port")]
+        port: u16,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        role: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Grpc {
+        port: u16,
+        #[serde(default)]
+        service: Option<String>,
+        #[serde(default)]
+        tls: bool,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    WebSocket {
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        send: Option<String>,
+        #[serde(default)]
+        expect: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Mail {
+        protocol: MailProtocol,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        tls: MailTls,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default = "default_cert_warn_days")]
+        cert_warn_days: u32,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Ntp {
+        #[serde(default = "default_ntp_port")]
+        port: u16,
+        #[serde(default = "default_ntp_max_offset_ms")]
+        max_offset_ms: f64,
+        #[serde(default = "default_ntp_max_stratum")]
+        max_stratum: u8,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Snmp {
+        #[serde(default = "default_snmp_port")]
+        port: u16,
+        oids: Vec<SnmpOid>,
+        #[serde(default)]
+        community: Option<String>,
+        #[serde(default)]
+        v3: Option<SnmpV3>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    ContentHash {
+        #[serde(default)]
+        url: Option<String>,
+        /// CSS selector whose matched elements' text is hashed instead of the whole page.
+        #[serde(default)]
+        selector: Option<String>,
+        /// Regex whose matches (or first capture group) are hashed.
+        #[serde(default)]
+        regex: Option<String>,
+        /// Regexes removed before hashing, for timestamps, nonces and similar churn.
+        #[serde(default)]
+        ignore: Vec<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    Mqtt {
+        /// Defaults to 1883, or 8883 with `tls`.
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        tls: bool,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        /// Probe topic to subscribe to and publish on for a round-trip measurement.
+        #[serde(default)]
+        topic: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
+    },
+    HttpFlow {
+        steps: Vec<FlowStep>,
+        /// Initial variables, available to every step as `{{name}}`.
+        #[serde(default)]
+        variables: HashMap<String, String>,
+        /// Per-step request timeout.
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+        #[serde(default)]
+        run_on: BTreeMap<String, String>,
+        #[serde(default)]
+        retry: Option<RetryPolicy>,
     },
 }
 
+/// One request of an `HttpFlow`. `url`, `headers` and `body` may reference `{{variables}}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlowStep {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "default_flow_method")]
+    pub method: String,
+    /// Absolute URL, or a path joined onto the server address.
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Required status code; any 2xx passes when unset.
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// JSON paths (`data.items.0.id`) and the values they must hold.
+    #[serde(default)]
+    pub expect_json: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub extract: Vec<FlowExtract>,
+}
+
+impl FlowStep {
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            // Only the path is used so the host stays out of masked output.
+            None => format!("{} {}", self.method.to_uppercase(), self.path()),
+        }
+    }
+
+    /// Path of the step's URL, which may still hold `{{var}}` templates that do not parse as a URL.
+    fn path(&self) -> String {
+        if let Ok(parsed) = reqwest::Url::parse(&self.url) {
+            return parsed.path().to_string();
+        }
+        let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+        let path = rest.find('/').map_or("/", |i| &rest[i..]);
+        path.split(['?', '#']).next().unwrap_or(path).to_string()
+    }
+}
+
+/// Saves part of a step's response into `var`. Exactly one source should be set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlowExtract {
+    pub var: String,
+    #[serde(default)]
+    pub json: Option<String>,
+    /// The first capture group, or the whole match if the pattern has none.
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub cookie: Option<String>,
+    #[serde(default)]
+    pub header: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnmpOid {
+    pub oid: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub expect: Option<String>,
+    #[serde(default)]
+    pub warn: Option<f64>,
+    #[serde(default)]
+    pub crit: Option<f64>,
+    /// Report counters as a per-second rate between polls.
+    #[serde(default)]
+    pub rate: bool,
+}
+
+impl SnmpOid {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.oid)
+    }
+}
+
+/// Splits a dotted OID into arcs, rejecting ones that cannot be BER-encoded.
+pub fn parse_oid(oid: &str) -> Result<Vec<u64>, String> {
+    let invalid = || format!("Invalid OID {}", oid);
+    let arcs: Vec<u64> = oid.trim_start_matches('.').split('.')
+        .map(|a| a.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    // The first two arcs share one subidentifier; only arc 2 may have a second arc of 40 or more.
+    match arcs[..] {
+        [first, second, ..] if first < 2 && second < 40 => Ok(arcs),
+        [2, second, ..] if second.checked_add(80).is_some() => Ok(arcs),
+        _ => Err(invalid()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnmpV3 {
+    pub username: String,
+    #[serde(default)]
+    pub auth_protocol: Option<SnmpAuthProtocol>,
+    #[serde(default)]
+    pub auth_password: Option<String>,
+    #[serde(default)]
+    pub priv_protocol: Option<SnmpPrivProtocol>,
+    #[serde(default)]
+    pub priv_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnmpAuthProtocol {
+    Md5,
+    Sha,
+    Sha256,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SnmpPrivProtocol {
+    Aes,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum MailProtocol {
+    Smtp,
+    Imap,
+    Pop3,
+}
+
+/// Mirrors libpq's `sslmode`: `prefer` uses TLS when the server offers it, `require` fails without it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PostgresTls {
+    #[default]
+    Disable,
+    Prefer,
+    Require,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum MailTls {
+    #[default]
+    None,
+    StartTls,
+    Implicit,
+}
+
+impl MailProtocol {
+    pub fn label(self) -> &'static str {
+        match self {
+            MailProtocol::Smtp => "SMTP",
+            MailProtocol::Imap => "IMAP",
+            MailProtocol::Pop3 => "POP3",
+        }
+    }
+
+    pub fn default_port(self, tls: MailTls) -> u16 {
+        match (self, tls) {
+            (MailProtocol::Smtp, MailTls::Implicit) => 465,
+            (MailProtocol::Smtp, _) => 25,
+            (MailProtocol::Imap, MailTls::Implicit) => 993,
+            (MailProtocol::Imap, _) => 143,
+            (MailProtocol::Pop3, MailTls::Implicit) => 995,
+            (MailProtocol::Pop3, _) => 110,
+        }
+    }
+}
+
+impl CheckType {
+    pub fn name(&self) -> String {
+        let binding = self.source_binding();
+        if binding.is_unbound() {
+            self.base_name()
+        } else {
+            // Keeps per-uplink variants of the same probe on separate result keys.
+            format!("{}@{}", self.base_name(), binding)
+        }
+    }
+
+    /// Source binding set on the check itself, without the server's fallback.
+    pub fn source_binding(&self) -> SourceBinding {
+        match self {
+            CheckType::Ping { source_address, interface, .. }
+            | CheckType::TcpPort { source_address, interface, .. }
+            | CheckType::UdpPort { source_address, interface, .. }
+            | CheckType::Http { source_address, interface, .. } => {
+                SourceBinding { address: *source_address, interface: interface.clone() }
+            }
+            _ => SourceBinding::default(),
+        }
+    }
+
+    /// Whether a server's source binding can be applied; these clients open their own sockets.
+    pub fn supports_binding(&self) -> bool {
+        !matches!(self, CheckType::MySql { .. } | CheckType::Redis { .. } | CheckType::Grpc { .. })
+    }
+
+    /// Node labels set on the check itself; host checks have none since they are pinned instead.
+    pub fn run_on(&self) -> Option<&BTreeMap<String, String>> {
+        match self {
+            CheckType::Ping { run_on, .. }
+            | CheckType::TcpPort { run_on, .. }
+            | CheckType::UdpPort { run_on, .. }
+            | CheckType::Http { run_on, .. }
+            | CheckType::Postgres { run_on, .. }
+            | CheckType::MySql { run_on, .. }
+            | CheckType::Redis { run_on, .. }
+            | CheckType::Grpc { run_on, .. }
+            | CheckType::WebSocket { run_on, .. }
+            | CheckType::Mail { run_on, .. }
+            | CheckType::Ntp { run_on, .. }
+            | CheckType::Snmp { run_on, .. }
+            | CheckType::ContentHash { run_on, .. }
+            | CheckType::Mqtt { run_on, .. }
+            | CheckType::HttpFlow { run_on, .. } => Some(run_on),
+            _ => None,
+        }
+    }
+
+    /// Retry policy set on the check itself, without the server's or global fallback.
+    pub fn retry(&self) -> Option<&RetryPolicy> {
+        match self {
+            CheckType::Ping { retry, .. }
+            | CheckType::TcpPort { retry, .. }
+            | CheckType::UdpPort { retry, .. }
+            | CheckType::Http { retry, .. }
+            | CheckType::Disk { retry, .. }
+            | CheckType::Memory { retry, .. }
+            | CheckType::Load { retry, .. }
+            | CheckType::Process { retry, .. }
+            | CheckType::ListeningPort { retry, .. }
+            | CheckType::FileAge { retry, .. }
+            | CheckType::LogMatch { retry, .. }
+            | CheckType::Command { retry, .. }
+            | CheckType::Postgres { retry, .. }
+            | CheckType::MySql { retry, .. }
+            | CheckType::Redis { retry, .. }
+            | CheckType::Grpc { retry, .. }
+            | CheckType::WebSocket { retry, .. }
+            | CheckType::Mail { retry, .. }
+            | CheckType::Ntp { retry, .. }
+            | CheckType::Snmp { retry, .. }
+            | CheckType::ContentHash { retry, .. }
+            | CheckType::Mqtt { retry, .. }
+            | CheckType::HttpFlow { retry, .. } => retry.as_ref(),
+        }
+    }
+
+    fn base_name(&self) -> String {
+        match self {
+            CheckType::Ping { .. } => "ICMP".into(),
+            CheckType::TcpPort { port, .. } => format!("TCP:{}", port),
+            CheckType::UdpPort { port, .. } => format!("UDP:{}", port),
+            CheckType::Http { method, .. } => format!("HTTP:{}", method.as_deref().unwrap_or("GET")),
+            CheckType::Disk { mount, .. } => format!("DISK:{}", mount),
+            CheckType::Memory { .. } => "MEMORY".into(),
+            CheckType::Load { .. } => "LOAD".into(),
+            CheckType::Process { name, .. } => format!("PROC:{}", name),
+            CheckType::ListeningPort { port, .. } => format!("LISTEN:{}", port),
+            CheckType::FileAge { path_glob, .. } => format!("FILE:{}", path_glob),
+            CheckType::LogMatch { path, regex, .. } => format!("LOG:{}~{}", path, regex),
+            CheckType::Command { argv, .. } => {
+                let program = argv.first().map(|p| p.rsplit(['/', '\\']).next().unwrap_or(p)).unwrap_or("?");
+                // One plugin is often run with different arguments; a digest keeps them apart without exposing them.
+                let digest = sha2::Sha256::digest(argv.join("\0").as_bytes());
+                format!("CMD:{}#{}", program, digest[..3].iter().map(|b| format!("{:02x}", b)).collect::<String>())
+            }
+            CheckType::Postgres { port, user, database, .. } => {
+                database_name("PGSQL", *port, database.as_deref(), user, &default_postgres_user())
+            }
+            CheckType::MySql { port, user, database, .. } => {
+                database_name("MYSQL", *port, database.as_deref(), user, &default_mysql_user())
+            }
+            CheckType::Redis { port, .. } => format!("REDIS:{}", port),
+            CheckType::Grpc { port, service: Some(service), .. } => format!("GRPC:{}/{}", port, service),
+            CheckType::Grpc { port, .. } => format!("GRPC:{}", port),
+            // Only the path is used so the host stays out of masked output.
+            CheckType::WebSocket { url: Some(url), .. } => match reqwest::Url::parse(url) {
+                Ok(parsed) => format!("WS:{}", parsed.path()),
+                Err(_) => "WS".into(),
+            },
+            CheckType::WebSocket { .. } => "WS".into(),
+            CheckType::Mail { protocol, port, tls, .. } => {
+                format!("{}:{}", protocol.label(), port.unwrap_or_else(|| protocol.default_port(*tls)))
+            }
+            CheckType::Ntp { port, .. } => format!("NTP:{}", port),
+            CheckType::Snmp { oids, .. } => format!("SNMP:{}", oids.first().map_or("?", |o| o.label())),
+            CheckType::Mqtt { port, tls, .. } => format!("MQTT:{}", port.unwrap_or_else(|| default_mqtt_port(*tls))),
+            CheckType::HttpFlow { steps, .. } => {
+                format!("FLOW:{}", steps.iter().map(|s| s.label()).collect::<Vec<_>>().join(">"))
+            }
+            CheckType::ContentHash { url, selector, regex, .. } => {
+                let path = url.as_deref().and_then(|u| reqwest::Url::parse(u).ok()).map(|u| u.path().to_string());
+                let mut name = format!("CONTENT:{}", path.as_deref().unwrap_or("/"));
+                // Each extraction watches a different slice of the page, so it needs its own snapshot key.
+                if let Some(selector) = selector {
+                    name.push_str(&format!("[{}]", selector));
+                }
+                if let Some(regex) = regex {
+                    name.push_str(&format!("~{}", regex));
+                }
+                name
+            }
+        }
+    }
+
+    /// Checks whose failures can be diagnosed with an ICMP path trace.
+    pub fn is_traceable(&self) -> bool {
+        matches!(self, CheckType::Ping { .. } | CheckType::TcpPort { .. })
+    }
+
+    /// Host checks read the local machine, so they are pinned to a node
+    /// instead of being sharded across the mesh.
+    pub fn is_local(&self) -> bool {
+        matches!(self,
+            CheckType::Disk { .. }
+            | CheckType::Memory { .. }
+            | CheckType::Load { .. }
+            | CheckType::Process { .. }
+            | CheckType::ListeningPort { .. }
+            | CheckType::FileAge { .. }
+            | CheckType::LogMatch { .. }
+            | CheckType::Command { .. })
+    }
+}
+
 pub fn default_ping_count() -> u32 { 1 }
 pub fn default_timeout() -> u64 { 3500 }
 
 pub fn default_http_method() -> Option<String> { Some("GET".to_string()) }
 pub fn default_http_status() -> Option<u16> { Some(200) }
 pub fn default_http_timeout() -> Option<u64> { Some(3500) }
+pub fn default_flow_method() -> String { "GET".to_string() }
+
+pub fn default_true() -> bool { true }
+
+pub fn default_disk_mount() -> String { "/".into() }
+pub fn default_disk_warn_pct() -> f64 { 80.0 }
+pub fn default_disk_crit_pct() -> f64 { 90.0 }
+pub fn default_memory_warn_pct() -> f64 { 85.0 }
+pub fn default_memory_crit_pct() -> f64 { 95.0 }
+pub fn default_load_warn() -> f64 { 1.0 }
+pub fn default_load_crit() -> f64 { 2.0 }
+pub fn default_process_min() -> u32 { 1 }
+pub fn default_log_window() -> u64 { 300 }
+pub fn default_log_threshold() -> u64 { 1 }
+
+pub fn default_postgres_port() -> u16 { 5432 }
+/// `PGSQL:5432/orders[reporting]`: one instance can host several databases and users worth checking.
+fn database_name(kind: &str, port: u16, database: Option<&str>, user: &str, default_user: &str) -> String {
+    let mut name = format!("{}:{}", kind, port);
+    if let Some(database) = database {
+        name.push_str(&format!("/{}", database));
+    }
+    if user != default_user {
+        name.push_str(&format!("[{}]", user));
+    }
+    name
+}
+
+pub fn default_postgres_user() -> String { "postgres".into() }
+pub fn default_mysql_port() -> u16 { 3306 }
+pub fn default_mysql_user() -> String { "root".into() }
+pub fn default_redis_port() -> u16 { 6379 }
+pub fn default_cert_warn_days() -> u32 { 14 }
+
+pub fn default_ntp_port() -> u16 { 123 }
+pub fn default_ntp_max_offset_ms() -> f64 { 100.0 }
+pub fn default_ntp_max_stratum() -> u8 { 15 }
+pub fn default_snmp_port() -> u16 { 161 }
+pub fn default_mqtt_port(tls: bool) -> u16 { if tls { 8883 } else { 1883 } }
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use sha2::{Digest, Sha256};
+use crate::models::NodeInfo;
+
+/// Uniform value in (0, 1) derived from a check key and a node id, the same on every node and build.
+fn unit_hash(key: &str, node: &str) -> f64 {
+    let digest = Sha256::new().chain_update(key).chain_update([0u8]).chain_update(node).finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    let bits = u64::from_be_bytes(bytes) >> 11;
+    (bits as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// Weighted rendezvous hashing: every node scores the key and the highest score owns it.
+/// A node joining or leaving only moves the keys it wins or held, about 1/N of them,
+/// and each node's share is proportional to its weight.
+pub fn owner<'a>(key: &str, nodes: &'a [(String, f64)]) -> Option<&'a str> {
+    nodes.iter()
+        .filter(|(_, weight)| *weight > 0.0)
+        .map(|(id, weight)| (id, -weight / unit_hash(key, id).ln()))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id.as_str())
+}
+
+/// Live nodes whose labels satisfy `selector`, with their weights, ready for [`owner`].
+pub fn eligible(nodes: &[(String, NodeInfo)], selector: &BTreeMap<String, String>) -> Vec<(String, f64)> {
+    nodes.iter()
+        .filter(|(_, info)| info.matches(selector))
+        .map(|(id, info)| (id.clone(), info.weight))
+        .collect()
+}
+
+/// `region=eu, net=internal`
+pub fn describe(selector: &BTreeMap<String, String>) -> String {
+    selector.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(spec: &[(&str, f64)]) -> Vec<(String, f64)> {
+        spec.iter().map(|(id, w)| (id.to_string(), *w)).collect()
+    }
+
+    fn keys() -> impl Iterator<Item = String> {
+        (0..10_000).map(|i| format!("web-10.0.{}.{}-TCP:443", i / 256, i % 256))
+    }
+
+    #[test]
+    fn owner_is_deterministic() {
+        let forward = nodes(&[("a", 1.0), ("b", 1.0), ("c", 1.0)]);
+        let reversed = nodes(&[("c", 1.0), ("b", 1.0), ("a", 1.0)]);
+        for key in keys().take(500) {
+            assert_eq!(owner(&key, &forward), owner(&key, &reversed));
+        }
+        assert_eq!(owner("k", &[]), None);
+        assert_eq!(owner("k", &nodes(&[("a", 0.0)])), None);
+    }
+
+    #[test]
+    fn removing_a_node_moves_only_its_keys() {
+        let before = nodes(&[("a", 1.0), ("b", 1.0), ("c", 1.0), ("d", 1.0)]);
+        let after = nodes(&[("a", 1.0), ("b", 1.0), ("d", 1.0)]);
+        let mut moved = 0;
+        for key in keys() {
+            let (old, new) = (owner(&key, &before).unwrap(), owner(&key, &after).unwrap());
+            if old == "c" {
+                moved += 1;
+            } else {
+                assert_eq!(old, new, "{} moved off a surviving node", key);
+            }
+        }
+        assert!((2_000..3_000).contains(&moved), "{} keys were on c", moved);
+    }
+
+    #[test]
+    fn share_follows_weight() {
+        let weighted = nodes(&[("a", 1.0), ("b", 2.0), ("c", 1.0)]);
+        let heavy = keys().filter(|k| owner(k, &weighted) == Some("b")).count();
+        // Expected share is 2/4 of the keys.
+        assert!((4_700..5_300).contains(&heavy), "b owned {} of 10000", heavy);
+    }
+}
@@ -1,34 +1,30 @@
 use std::time::Duration;
-use std::net::IpAddr;
-use chrono::Utc;
+use std::net::{IpAddr, SocketAddr};
 use futures::stream::{FuturesUnordered, StreamExt};
-use tokio::net::TcpStream;
 use surge_ping::{PingIdentifier, PingSequence};
-use crate::config::{Server, CheckType};
-use crate::models::CheckResult;
-use crate::engine::Monitor;
+use crate::config::{Server, CheckType, ProxyConfig, RetryPolicy, SourceBinding};
+use crate::models::{CheckResult, ProbeOutcome, Status};
+use crate::engine::{command, database, flow, grpc, host, mail, mqtt, net, ntp, websocket, Monitor};
+use crate::engine::net::Route;
 
 impl Monitor {
     pub async fn run_check_with_retry(&self, server: Server, target_address: String, check: CheckType) -> CheckResult {
-        let mut last_result = self.perform_check(&server, &target_address, &check).await;
-        
-        if !last_result.status && server.max_retries > 0 {
-            for _ in 1..=server.max_retries {
-                tokio::time::sleep(Duration::from_millis(50)).await;
-                last_result = self.perform_check(&server, &target_address, &check).await;
-                if last_result.status {
-                    break;
-                }
-            }
-        }
-        last_result
+        let (policy, interval) = {
+            let cfg = self.config.read().await;
+            (server.retry_policy(&check, cfg.retry.as_ref()), cfg.check_interval)
+        };
+        let attempts = policy.attempts.unwrap_or(server.max_retries.saturating_add(1)).max(1);
+        let budget = Duration::from_millis(policy.deadline_ms.unwrap_or(interval * 1000));
+        let blank = || CheckResult::new(&server.name, &server.address, &target_address, check.name());
+        retry_within(&policy, attempts, budget, blank, || self.perform_check(&server, &target_address, &check)).await
     }
 
     pub async fn perform_check(&self, server: &Server, target_address: &str, check: &CheckType) -> CheckResult {
-        let timestamp = Utc::now();
+        let mut result = CheckResult::new(&server.name, &server.address, target_address, check.name());
+        let bind = check.source_binding().or(server.source_binding());
         match check {
-            CheckType::Ping { count, timeout_ms, simulate_loss } => {
-                let (mut status, mut latency, mut loss, mut msg) = self.check_ping(target_address, *count, *timeout_ms, *simulate_loss).await;
+            CheckType::Ping { count, timeout_ms, simulate_loss, .. } => {
+                let (mut status, mut latency, mut loss, mut msg) = self.check_ping(target_address, *count, *timeout_ms, *simulate_loss, &bind).await;
                 
                 if !status {
                     const DISCOVERY_PORTS: &[(u16, &str)] = &[
@@ -40,8 +36,9 @@ impl Monitor {
                     let mut discovery_tasks = FuturesUnordered::new();
                     for &(port, name) in DISCOVERY_PORTS {
                         let target = target_address.to_string();
+                        let route = Route { bind: bind.clone(), proxy: None };
                         discovery_tasks.push(async move {
-                            let (p_status, p_latency, _) = Self::raw_tcp_check(&target, port, 1200).await;
+                            let (p_status, p_latency, _) = Self::raw_tcp_check(&target, port, 1200, &route).await;
                             (p_status, p_latency, name)
                         });
                     }
@@ -59,97 +56,158 @@ impl Monitor {
                     }
                 }
 
-                CheckResult {
-                    category: String::new(),
-                    server_name: server.name.clone(),
-                    parent_address: server.address.clone(),
-                    target_address: target_address.to_string(),
-                    timestamp,
-                    check_type: "ICMP".into(),
-                    status,
-                    latency_ms: latency,
-                    packet_loss: loss,
-                    message: msg,
-                    category_order: 0,
-                    server_order: 0,
-                    check_order: 0,
-                    provider_node: None,
-                }
+                result.set_health(Status::from_bool(status));
+                result.latency_ms = latency;
+                result.packet_loss = loss;
+                result.message = msg;
             }
-            CheckType::TcpPort { port, count, timeout_ms, simulate_loss } => {
-                let (status, latency, loss, msg) = self.check_tcp_port(target_address, *port, *count, *timeout_ms, *simulate_loss).await;
-                CheckResult {
-                    category: String::new(),
-                    server_name: server.name.clone(),
-                    parent_address: server.address.clone(),
-                    target_address: target_address.to_string(),
-                    timestamp,
-                    check_type: format!("TCP:{}", port),
-                    status,
-                    latency_ms: latency,
-                    packet_loss: Some(loss),
-                    message: msg,
-                    category_order: 0,
-                    server_order: 0,
-                    check_order: 0,
-                    provider_node: None,
-                }
+            CheckType::TcpPort { port, count, timeout_ms, simulate_loss, proxy, .. } => {
+                let route = Route { bind, proxy: self.proxy_for(proxy.as_ref(), target_address).await };
+                let (status, latency, loss, msg) = self.check_tcp_port(target_address, *port, *count, *timeout_ms, *simulate_loss, &route).await;
+                result.set_health(Status::from_bool(status));
+                result.latency_ms = latency;
+                result.packet_loss = Some(loss);
+                result.message = msg;
             }
-            CheckType::UdpPort { port, count, timeout_ms, simulate_loss } => {
-                let (status, latency, loss, msg) = self.check_udp_port(target_address, *port, *count, *timeout_ms, *simulate_loss).await;
-                CheckResult {
-                    category: String::new(),
-                    server_name: server.name.clone(),
-                    parent_address: server.address.clone(),
-                    target_address: target_address.to_string(),
-                    timestamp,
-                    check_type: format!("UDP:{}", port),
-                    status,
-                    latency_ms: latency,
-                    packet_loss: Some(loss),
-                    message: msg,
-                    category_order: 0,
-                    server_order: 0,
-                    check_order: 0,
-                    provider_node: None,
-                }
+            CheckType::UdpPort { port, count, timeout_ms, simulate_loss, .. } => {
+                let (status, latency, loss, msg) = self.check_udp_port(target_address, *port, *count, *timeout_ms, *simulate_loss, &bind).await;
+                result.set_health(Status::from_bool(status));
+                result.latency_ms = latency;
+                result.packet_loss = Some(loss);
+                result.message = msg;
             }
-            CheckType::Http { method, expected_status, contains, timeout_ms } => {
+            CheckType::Http { method, expected_status, contains, timeout_ms, proxy, .. } => {
                 let url = if target_address.starts_with("http://") || target_address.starts_with("https://") {
                     target_address.to_string()
                 } else {
                     format!("http://{}", target_address)
                 };
-                let (status, latency, msg) = self.check_http(&url, method.as_deref(), *expected_status, contains.as_deref(), *timeout_ms).await;
-                CheckResult {
-                    category: String::new(),
-                    server_name: server.name.clone(),
-                    parent_address: server.address.clone(),
-                    target_address: target_address.to_string(),
-                    timestamp,
-                    check_type: format!("HTTP:{}", method.as_deref().unwrap_or("GET")),
-                    status,
-                    latency_ms: latency,
-                    packet_loss: if status { Some(0.0) } else { Some(100.0) },
-                    message: msg,
-                    category_order: 0,
-                    server_order: 0,
-                    check_order: 0,
-                    provider_node: None,
-                }
+                let host = reqwest::Url::parse(&url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+                let route = Route { bind, proxy: self.proxy_for(proxy.as_ref(), &host).await };
+                let (status, latency, msg) = self.check_http(&url, method.as_deref(), *expected_status, contains.as_deref(), *timeout_ms, &route).await;
+                result.set_health(Status::from_bool(status));
+                result.latency_ms = latency;
+                result.packet_loss = if status { Some(0.0) } else { Some(100.0) };
+                result.message = msg;
+            }
+            CheckType::Disk { .. }
+            | CheckType::Memory { .. }
+            | CheckType::Load { .. }
+            | CheckType::Process { .. }
+            | CheckType::ListeningPort { .. }
+            | CheckType::FileAge { .. } => {
+                result.apply(host::check_host(check.clone()).await);
+            }
+            CheckType::LogMatch { .. } => {
+                let key = format!("{}-{}-{}-{}", server.name, server.address, target_address, result.check_type);
+                result.apply(self.check_log(&key, check).await);
+            }
+            CheckType::Command { argv, timeout_ms, env, .. } => {
+                result.apply(command::check_command(argv, *timeout_ms, env, target_address).await);
+            }
+            CheckType::Postgres { .. } | CheckType::MySql { .. } | CheckType::Redis { .. } => {
+                let outcome = database::check_database(target_address, check, &bind).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::Grpc { port, service, tls, timeout_ms, .. } => {
+                let outcome = grpc::check_grpc(target_address, *port, service.as_deref(), *tls, *timeout_ms).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::WebSocket { url, send, expect, timeout_ms, .. } => {
+                let url = match url {
+                    Some(u) => u.clone(),
+                    None if target_address.starts_with("ws://") || target_address.starts_with("wss://") => target_address.to_string(),
+                    None => format!("ws://{}", target_address),
+                };
+                let outcome = websocket::check_websocket(&url, send.as_deref(), expect.as_deref(), *timeout_ms, &bind).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::Mail { .. } => {
+                let outcome = mail::check_mail(target_address, check, &bind).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::Ntp { port, max_offset_ms, max_stratum, timeout_ms, .. } => {
+                let outcome = match self.resolve(target_address).await {
+                    Ok(ip) => ntp::check_ntp(ip, *port, *max_offset_ms, *max_stratum, *timeout_ms, &bind).await,
+                    Err(e) => ProbeOutcome::new(Status::Down, format!("Domain Resolution Error: {}", e)),
+                };
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::Snmp { .. } => {
+                let key = format!("{}-{}-{}-{}", server.name, server.address, target_address, result.check_type);
+                let outcome = match self.resolve(target_address).await {
+                    Ok(ip) => self.check_snmp(&key, ip, check, &bind).await,
+                    Err(e) => ProbeOutcome::new(Status::Down, format!("Domain Resolution Error: {}", e)),
+                };
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::Mqtt { .. } => {
+                let outcome = mqtt::check_mqtt(target_address, check, &bind).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::HttpFlow { steps, variables, timeout_ms, .. } => {
+                let base = if target_address.starts_with("http://") || target_address.starts_with("https://") {
+                    target_address.to_string()
+                } else {
+                    format!("http://{}", target_address)
+                };
+                let host = reqwest::Url::parse(&base).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+                let route = Route { bind, proxy: self.proxy_for(None, &host).await };
+                let outcome = flow::run_flow(&route, &base, steps, variables, *timeout_ms).await;
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.apply(outcome);
+            }
+            CheckType::ContentHash { url, .. } => {
+                let url = match url {
+                    Some(u) => u.clone(),
+                    None if target_address.starts_with("http://") || target_address.starts_with("https://") => target_address.to_string(),
+                    None => format!("http://{}", target_address),
+                };
+                let host = reqwest::Url::parse(&url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+                let route = Route { bind, proxy: self.proxy_for(None, &host).await };
+                let (outcome, change) = match self.http_client_for(&route) {
+                    Ok(client) => {
+                        let key = format!("{}-{}-{}-{}", server.name, server.address, target_address, result.check_type);
+                        self.check_content(&key, &url, &client, check).await
+                    }
+                    Err(e) => (ProbeOutcome::new(Status::Down, format!("Source Bind Failed: {}", e)), None),
+                };
+                result.packet_loss = Some(if outcome.health == Status::Down { 100.0 } else { 0.0 });
+                result.content_change = change;
+                result.apply(outcome);
+            }
         }
+        result
     }
-}
 
-    pub async fn check_ping(&self, address: &str, count: u32, timeout_ms: u64, simulate_loss: Option<f64>) -> (bool, Option<f64>, Option<f64>, String) {
+    #[allow(clippy::collapsible_if, clippy::single_match)]
+    pub async fn check_ping(&self, address: &str, count: u32, timeout_ms: u64, simulate_loss: Option<f64>, bind: &SourceBinding) -> (bool, Option<f64>, Option<f64>, String) {
         let ip = match self.resolve(address).await {
             Ok(ip) => ip,
             Err(e) => return (false, None, None, format!("Domain Resolution Error: {}", e)),
         };
 
+        let bound;
+        let client = if bind.is_unbound() {
+            &self.ping_client
+        } else {
+            bound = match self.bound_ping_client(bind, ip) {
+                Ok(c) => c,
+                Err(e) => return (false, None, None, format!("Source Bind Failed: {}", e)),
+            };
+            &*bound
+        };
+
         let payload = [0u8; 56];
         let pinger_id = PingIdentifier(rand::random());
-        let mut pinger = self.ping_client.pinger(ip, pinger_id).await;
+        let mut pinger = client.pinger(ip, pinger_id).await;
         pinger.timeout(Duration::from_millis(timeout_ms));
 
         let _ = pinger.ping(PingSequence(0xFFFF), &payload).await;
@@ -201,16 +259,23 @@ impl Monitor {
         }
     }
 
-    pub async fn raw_tcp_check(address: &str, port: u16, timeout_ms: u64) -> (bool, Option<f64>, String) {
+    pub async fn raw_tcp_check(address: &str, port: u16, timeout_ms: u64, route: &Route) -> (bool, Option<f64>, String) {
         let addr = format!("{}:{}", address, port);
         let mut last_error = String::from("Timeout");
         
         for attempt in 0..2 {
             let start = std::time::Instant::now();
-            match tokio::time::timeout(Duration::from_millis(timeout_ms), TcpStream::connect(&addr)).await {
-                Ok(Ok(_)) => return (true, Some(start.elapsed().as_secs_f64() * 1000.0), "Connection Established".into()),
-                Ok(Err(e)) => last_error = format!("Connection Rejected: {}", e),
-                Err(_) => last_error = "Request Timeout".into(),
+            if let Some(proxy) = &route.proxy {
+                match net::connect_via_proxy(proxy, address, port, &route.bind, Duration::from_millis(timeout_ms)).await {
+                    Ok(_) => return (true, Some(start.elapsed().as_secs_f64() * 1000.0), "Connection Established via Proxy".into()),
+                    Err(e) => last_error = e.to_string(),
+                }
+            } else {
+                match tokio::time::timeout(Duration::from_millis(timeout_ms), net::connect_tcp(&addr, &route.bind)).await {
+                    Ok(Ok(_)) => return (true, Some(start.elapsed().as_secs_f64() * 1000.0), "Connection Established".into()),
+                    Ok(Err(e)) => last_error = format!("Connection Rejected: {}", e),
+                    Err(_) => last_error = "Request Timeout".into(),
+                }
             }
             
             if attempt == 0 {
@@ -220,7 +285,8 @@ impl Monitor {
         (false, None, last_error)
     }
 
-    pub async fn check_tcp_port(&self, address: &str, port: u16, count: u32, timeout_ms: u64, simulate_loss: Option<f64>) -> (bool, Option<f64>, f64, String) {
+    #[allow(clippy::collapsible_if)]
+    pub async fn check_tcp_port(&self, address: &str, port: u16, count: u32, timeout_ms: u64, simulate_loss: Option<f64>, route: &Route) -> (bool, Option<f64>, f64, String) {
         let mut received = 0;
         let mut total_latency = 0.0;
         let mut last_error = String::from("Connection Rejected");
@@ -232,7 +298,7 @@ impl Monitor {
                     continue;
                 }
             }
-            let (status, latency, msg) = Self::raw_tcp_check(address, port, timeout_ms).await;
+            let (status, latency, msg) = Self::raw_tcp_check(address, port, timeout_ms, route).await;
             if status {
                 received += 1;
                 total_latency += latency.unwrap_or(0.0);
@@ -268,10 +334,16 @@ impl Monitor {
         }
     }
 
-    pub async fn check_udp_port(&self, address: &str, port: u16, count: u32, timeout_ms: u64, simulate_loss: Option<f64>) -> (bool, Option<f64>, f64, String) {
+    #[allow(clippy::collapsible_if)]
+    pub async fn check_udp_port(&self, address: &str, port: u16, count: u32, timeout_ms: u64, simulate_loss: Option<f64>, bind: &SourceBinding) -> (bool, Option<f64>, f64, String) {
         let mut received = 0;
         let mut total_latency = 0.0;
         let mut last_error = String::from("Connection Rejected");
+        // Resolved up front so the socket is bound in the target's address family.
+        let addr = match self.resolve(address).await {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(e) => return (false, None, 100.0, format!("Domain Resolution Error: {}", e)),
+        };
 
         for i in 0..count {
             if let Some(sim_loss) = simulate_loss {
@@ -280,13 +352,12 @@ impl Monitor {
                     continue;
                 }
             }
-            let addr = format!("{}:{}", address, port);
             let start = std::time::Instant::now();
-            let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await;
+            let socket = net::bind_udp(bind, addr.ip()).await;
             
             match socket {
                 Ok(s) => {
-                    if tokio::time::timeout(Duration::from_millis(timeout_ms), s.send_to(&[], &addr)).await.is_ok() {
+                    if tokio::time::timeout(Duration::from_millis(timeout_ms), s.send_to(&[], addr)).await.is_ok() {
                         received += 1;
                         total_latency += start.elapsed().as_secs_f64() * 1000.0;
                     } else {
@@ -324,7 +395,13 @@ impl Monitor {
         }
     }
 
-    pub async fn check_http(&self, url: &str, method: Option<&str>, expected_status: Option<u16>, contains: Option<&str>, timeout_ms: Option<u64>) -> (bool, Option<f64>, String) {
+    pub async fn check_http(&self, url: &str, method: Option<&str>, expected_status: Option<u16>, contains: Option<&str>, timeout_ms: Option<u64>, route: &Route) -> (bool, Option<f64>, String) {
+        let client = match self.http_client_for(route) {
+            Ok(c) => c,
+            Err(e) if e.starts_with("Invalid proxy") => return (false, None, format!("Proxy Failure: {}", e)),
+            Err(e) => return (false, None, format!("Source Bind Failed: {}", e)),
+        };
+
         let method_str = method.unwrap_or("GET");
         let http_method = match method_str.to_uppercase().as_str() {
             "POST" => reqwest::Method::POST,
@@ -337,7 +414,7 @@ impl Monitor {
         };
 
         let timeout = Duration::from_millis(timeout_ms.unwrap_or(3500));
-        let request = self.http_client.request(http_method, url).timeout(timeout).build();
+        let request = client.request(http_method, url).timeout(timeout).build();
 
         let req = match request {
             Ok(r) => r,
@@ -345,10 +422,13 @@ impl Monitor {
         };
 
         let start = std::time::Instant::now();
-        match self.http_client.execute(req).await {
+        match client.execute(req).await {
             Ok(response) => {
                 let latency = start.elapsed().as_secs_f64() * 1000.0;
                 let status_code = response.status().as_u16();
+                if status_code == 407 && route.proxy.is_some() {
+                    return (false, Some(latency), "Proxy Failure: Authentication Required (407)".into());
+                }
                 
                 let expected_stat = expected_status.unwrap_or(200);
                 if status_code != expected_stat {
@@ -375,6 +455,8 @@ impl Monitor {
             Err(e) => {
                 let err_msg = if e.is_timeout() {
                     "Request Timeout".to_string()
+                } else if let Some(proxy) = &route.proxy {
+                    self.diagnose_proxy_error(url, proxy, &route.bind, timeout, &e).await
                 } else {
                     format!("Request error: {}", e)
                 };
@@ -383,6 +465,23 @@ impl Monitor {
         }
     }
 
+    /// Works out whether a failed proxied request died at the proxy or beyond it.
+    async fn diagnose_proxy_error(&self, url: &str, proxy: &ProxyConfig, bind: &SourceBinding, timeout: Duration, e: &reqwest::Error) -> String {
+        let Ok(target) = reqwest::Url::parse(url) else {
+            return format!("Request error via proxy: {}", e);
+        };
+        // Plain-HTTP targets are forwarded rather than tunnelled, so the proxy itself answered badly.
+        if target.scheme() == "http" && proxy.url.starts_with("http://") {
+            return format!("Proxy Failure: {}", e);
+        }
+        let host = target.host_str().unwrap_or_default().to_string();
+        let port = target.port_or_known_default().unwrap_or(80);
+        match net::connect_via_proxy(proxy, &host, port, bind, timeout).await {
+            Err(proxy_error) => proxy_error.to_string(),
+            Ok(_) => format!("Request error via proxy: {}", e),
+        }
+    }
+
     pub async fn resolve(&self, address: &str) -> Result<IpAddr, String> {
         if let Ok(ip) = address.parse::<IpAddr>() { return Ok(ip); }
         match self.dns_resolver.lookup_ip(address).await {
@@ -391,3 +490,118 @@ impl Monitor {
         }
     }
 }
+
+/// Runs `attempt` until it passes, `attempts` are used up or `budget` runs out, spacing tries per `policy`.
+async fn retry_within<F, Fut>(policy: &RetryPolicy, attempts: u32, budget: Duration, blank: impl Fn() -> CheckResult, mut attempt: F) -> CheckResult
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = CheckResult>,
+{
+    let deadline = tokio::time::Instant::now() + budget;
+    let mut last_result: Option<CheckResult> = None;
+    for n in 1..=attempts {
+        if n > 1 {
+            let spread = 1.0 + policy.jitter * (rand::random::<f64>() * 2.0 - 1.0);
+            let delay = policy.backoff(n - 1).mul_f64(spread);
+            // A retry that cannot start before the deadline would only delay the verdict.
+            if tokio::time::Instant::now() + delay >= deadline {
+                break;
+            }
+            tokio::time::sleep(delay).await;
+        }
+        match tokio::time::timeout_at(deadline, attempt()).await {
+            Ok(mut result) => {
+                result.attempts = Some(n);
+                result.succeeded_on_retry = result.status && n > 1;
+                if result.status {
+                    return result;
+                }
+                last_result = Some(result);
+            }
+            Err(_) => {
+                let mut result = last_result.unwrap_or_else(&blank);
+                result.set_health(Status::Down);
+                result.message = if result.message.is_empty() {
+                    format!("Deadline Exceeded ({}ms)", budget.as_millis())
+                } else {
+                    format!("{} (deadline exceeded on attempt {})", result.message, n)
+                };
+                result.attempts = Some(n);
+                return result;
+            }
+        }
+    }
+    last_result.unwrap_or_else(blank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Instant;
+
+    fn server(json: &str) -> Server {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn failing(message: &str) -> CheckResult {
+        let mut result = CheckResult::new("s", "a", "t", "TCP:1".into());
+        result.set_health(Status::Down);
+        result.message = message.into();
+        result
+    }
+
+    #[test]
+    fn retry_policy_precedence() {
+        let global = RetryPolicy { initial_delay_ms: 1, ..RetryPolicy::fixed_gap() };
+        let s = server(r#"{ "name": "s", "address": "a", "retry": { "initial_delay_ms": 2 }, "checks": [
+            { "type": "TcpPort", "port": 1, "retry": { "initial_delay_ms": 3 } },
+            { "type": "TcpPort", "port": 2 } ] }"#);
+        assert_eq!(s.retry_policy(&s.checks[0], Some(&global)).initial_delay_ms, 3);
+        assert_eq!(s.retry_policy(&s.checks[1], Some(&global)).initial_delay_ms, 2);
+
+        let bare = server(r#"{ "name": "s", "address": "a", "checks": [{ "type": "TcpPort", "port": 2 }] }"#);
+        assert_eq!(bare.retry_policy(&bare.checks[0], Some(&global)).initial_delay_ms, 1);
+
+        // Nothing configured keeps the original fixed gap rather than the policy's backoff defaults.
+        let fixed = bare.retry_policy(&bare.checks[0], None);
+        assert_eq!((fixed.backoff(1), fixed.backoff(3)), (Duration::from_millis(50), Duration::from_millis(50)));
+        assert_eq!(fixed.jitter, 0.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unset_policy_retries_fifty_ms_apart() {
+        let start = Instant::now();
+        let mut tries = Vec::new();
+        let result = retry_within(&RetryPolicy::fixed_gap(), 3, Duration::from_secs(5), || failing(""), || {
+            tries.push(start.elapsed());
+            async { failing("Connection Refused") }
+        }).await;
+        assert_eq!(tries, [0, 50, 100].map(Duration::from_millis));
+        assert_eq!(result.attempts, Some(3));
+        assert!(!result.status);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deadline_cuts_retries_short() {
+        // Retries that could not start before the deadline are skipped.
+        let policy = RetryPolicy { initial_delay_ms: 400, max_delay_ms: 400, ..RetryPolicy::fixed_gap() };
+        let mut tries = 0;
+        let result = retry_within(&policy, 5, Duration::from_millis(1000), || failing(""), || {
+            tries += 1;
+            async { failing("Connection Refused") }
+        }).await;
+        assert_eq!((tries, result.attempts), (3, Some(3)));
+        assert_eq!(result.message, "Connection Refused");
+
+        // A try still running at the deadline is cut off and the check is Down.
+        let start = Instant::now();
+        let result = retry_within(&RetryPolicy::fixed_gap(), 5, Duration::from_millis(1000), || failing(""), || async {
+            tokio::time::sleep(Duration::from_millis(700)).await;
+            failing("Timeout")
+        }).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(1000));
+        assert_eq!(result.attempts, Some(2));
+        assert_eq!(result.message, "Timeout (deadline exceeded on attempt 2)");
+        assert_eq!(result.health, Status::Down);
+    }
+}
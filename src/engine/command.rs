@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use crate::models::{ProbeOutcome, Status};
+
+const MAX_MESSAGE_LEN: usize = 512;
+
+/// Runs a Nagios-compatible plugin and maps its exit code to a status.
+pub async fn check_command(argv: &[String], timeout_ms: u64, env: &HashMap<String, String>, address: &str) -> ProbeOutcome {
+    let Some((program, args)) = argv.split_first() else {
+        return ProbeOutcome::new(Status::Unknown, "Command check has an empty argv");
+    };
+
+    let mut cmd = Command::new(program);
+    cmd.args(args.iter().map(|a| a.replace("{address}", address)))
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let start = std::time::Instant::now();
+    let child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Plugin Launch Fault ({}): {}", program, e)),
+    };
+
+    let output = match tokio::time::timeout(Duration::from_millis(timeout_ms), child.wait_with_output()).await {
+        Ok(Ok(o)) => o,
+        Ok(Err(e)) => return ProbeOutcome::new(Status::Unknown, format!("Plugin I/O Fault: {}", e)),
+        Err(_) => return ProbeOutcome::new(Status::Unknown, format!("Plugin Timeout after {}ms", timeout_ms)),
+    };
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+    let health = exit_health(output.status.code());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (text, metrics) = parse_plugin_output(&stdout);
+    let message = if !text.is_empty() {
+        text
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match (stderr.lines().next().map(str::trim), output.status.code()) {
+            (Some(line), _) if !line.is_empty() => line.to_string(),
+            (_, Some(code)) => format!("Plugin exited with code {}", code),
+            (_, None) => "Plugin terminated by signal".into(),
+        }
+    };
+
+    let mut outcome = ProbeOutcome::new(health, truncate(&message));
+    outcome.latency_ms = Some(elapsed);
+    outcome.metrics = metrics;
+    outcome
+}
+
+/// Nagios plugin exit codes; anything other than 0-2, or death by signal, is Unknown.
+fn exit_health(code: Option<i32>) -> Status {
+    match code {
+        Some(0) => Status::Up,
+        Some(1) => Status::Degraded,
+        Some(2) => Status::Down,
+        _ => Status::Unknown,
+    }
+}
+
+/// Splits plugin output into its status text and perfdata metrics.
+///
+/// The first line is `TEXT | PERFDATA`; following lines are long text until one
+/// has its own `|`, after which that line's tail and every later line are perfdata.
+pub fn parse_plugin_output(stdout: &str) -> (String, BTreeMap<String, f64>) {
+    let mut metrics = BTreeMap::new();
+    let mut lines = stdout.lines();
+
+    let first = lines.next().unwrap_or_default();
+    let (text, perf) = first.split_once('|').unwrap_or((first, ""));
+    parse_perfdata(perf, &mut metrics);
+
+    let mut in_perf = false;
+    for line in lines {
+        if in_perf {
+            parse_perfdata(line, &mut metrics);
+        } else if let Some((_, perf)) = line.split_once('|') {
+            parse_perfdata(perf, &mut metrics);
+            in_perf = true;
+        }
+    }
+    (text.trim().to_string(), metrics)
+}
+
+/// Parses `'label'=value[UOM];[warn];[crit];[min];[max]` entries.
+pub fn parse_perfdata(perf: &str, metrics: &mut BTreeMap<String, f64>) {
+    for entry in split_perf_entries(perf) {
+        let Some((label, data)) = entry.rsplit_once('=') else { continue };
+        let label = label.trim().trim_matches('\'');
+        if label.is_empty() {
+            continue;
+        }
+
+        let mut fields = data.split(';');
+        let Some(value) = fields.next().and_then(parse_number) else { continue };
+        metrics.insert(label.to_string(), value);
+
+        if let Some(warn) = fields.next().and_then(parse_number) {
+            metrics.insert(format!("{}.warn", label), warn);
+        }
+        if let Some(crit) = fields.next().and_then(parse_number) {
+            metrics.insert(format!("{}.crit", label), crit);
+        }
+    }
+}
+
+fn split_perf_entries(perf: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in perf.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    entries.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        entries.push(current);
+    }
+    entries
+}
+
+/// Reads the leading number of a perfdata field, dropping its unit of measure.
+fn parse_number(field: &str) -> Option<f64> {
+    let end = field
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+        .unwrap_or(field.len());
+    field[..end].parse().ok()
+}
+
+fn truncate(message: &str) -> String {
+    if message.len() <= MAX_MESSAGE_LEN {
+        return message.to_string();
+    }
+    let mut end = MAX_MESSAGE_LEN;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &message[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perf(s: &str) -> BTreeMap<String, f64> {
+        let mut metrics = BTreeMap::new();
+        parse_perfdata(s, &mut metrics);
+        metrics
+    }
+
+    #[test]
+    fn quoted_label_with_spaces() {
+        let m = perf("'disk usage /var'=42%;80;90;0;100 load1=0.5");
+        assert_eq!(m["disk usage /var"], 42.0);
+        assert_eq!(m["disk usage /var.warn"], 80.0);
+        assert_eq!(m["disk usage /var.crit"], 90.0);
+        assert_eq!(m["load1"], 0.5);
+    }
+
+    #[test]
+    fn unit_suffixes_are_dropped() {
+        let m = perf("time=0.123s size=512KB count=7c used=-3.5MB");
+        assert_eq!(m["time"], 0.123);
+        assert_eq!(m["size"], 512.0);
+        assert_eq!(m["count"], 7.0);
+        assert_eq!(m["used"], -3.5);
+    }
+
+    #[test]
+    fn empty_thresholds_are_skipped() {
+        let m = perf("rta=12.5ms;;200 pl=0%;;;;");
+        assert_eq!(m["rta"], 12.5);
+        assert!(!m.contains_key("rta.warn"));
+        assert_eq!(m["rta.crit"], 200.0);
+        assert_eq!(m["pl"], 0.0);
+        assert!(!m.contains_key("pl.warn") && !m.contains_key("pl.crit"));
+    }
+
+    #[test]
+    fn trailing_semicolons_and_bad_values() {
+        let m = perf("conns=10;;; bogus=U empty= =5");
+        assert_eq!(m.len(), 1);
+        assert_eq!(m["conns"], 10.0);
+    }
+
+    #[test]
+    fn first_line_is_text_and_later_lines_add_perfdata() {
+        let (text, m) = parse_plugin_output("OK - all fine | a=1\nlong text | b=2;3;4\nmore\n");
+        assert_eq!(text, "OK - all fine");
+        assert_eq!(m["a"], 1.0);
+        assert_eq!(m["b"], 2.0);
+        assert_eq!(m["b.crit"], 4.0);
+    }
+
+    #[test]
+    fn perfdata_continues_on_lines_without_a_pipe() {
+        let output = "DISK OK | /=10%\n/var is fine\n/home is fine | /var=20%;80;90\n/home=30%;80;90\n'tmp space'=5%\n";
+        let (text, m) = parse_plugin_output(output);
+        assert_eq!(text, "DISK OK");
+        assert_eq!(m["/"], 10.0);
+        assert_eq!(m["/var"], 20.0);
+        assert_eq!(m["/home"], 30.0);
+        assert_eq!(m["/home.crit"], 90.0);
+        assert_eq!(m["tmp space"], 5.0);
+        assert_eq!(m.len(), 8);
+    }
+
+    #[test]
+    fn exit_codes_map_to_status() {
+        assert_eq!(exit_health(Some(0)), Status::Up);
+        assert_eq!(exit_health(Some(1)), Status::Degraded);
+        assert_eq!(exit_health(Some(2)), Status::Down);
+        assert_eq!(exit_health(Some(3)), Status::Unknown);
+        assert_eq!(exit_health(Some(4)), Status::Unknown);
+        assert_eq!(exit_health(Some(-1)), Status::Unknown);
+        assert_eq!(exit_health(None), Status::Unknown);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn plugin_exit_code_and_output() {
+        let argv = ["sh", "-c", "echo 'WARNING - {address} slow | t=3s;2;5'; exit 1"].map(String::from);
+        let outcome = check_command(&argv, 5000, &HashMap::new(), "db1").await;
+        assert_eq!(outcome.health, Status::Degraded);
+        assert_eq!(outcome.message, "WARNING - db1 slow");
+        assert_eq!(outcome.metrics["t.warn"], 2.0);
+
+        let argv = ["sh", "-c", "exit 7"].map(String::from);
+        let outcome = check_command(&argv, 5000, &HashMap::new(), "db1").await;
+        assert_eq!(outcome.health, Status::Unknown);
+        assert_eq!(outcome.message, "Plugin exited with code 7");
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+use chrono::Utc;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use crate::config::CheckType;
+use crate::engine::Monitor;
+use crate::models::{ContentChange, ContentSnapshot, ProbeOutcome, Status};
+
+/// Elements whose text never reaches the reader and churns on every render.
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+const DIFF_LINES: usize = 12;
+const DIFF_CHARS: usize = 900;
+
+fn visible_text(root: ElementRef) -> Vec<String> {
+    root.descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node.ancestors()
+                .any(|a| a.value().as_element().is_some_and(|e| HIDDEN_ELEMENTS.contains(&e.name())));
+            (!hidden).then(|| text.to_string())
+        })
+        .collect()
+}
+
+/// Reduces a response body to the text worth comparing, one trimmed line per entry.
+pub fn normalize(body: &str, is_html: bool, selector: Option<&str>, regex: Option<&str>, ignore: &[String]) -> Result<String, String> {
+    let mut text = if let Some(raw) = selector {
+        let selector = Selector::parse(raw).map_err(|e| format!("Invalid Selector '{}': {}", raw, e))?;
+        let document = Html::parse_document(body);
+        let matched: Vec<String> = document.select(&selector).flat_map(visible_text).collect();
+        if matched.is_empty() {
+            return Err(format!("Selector '{}' Matched Nothing", raw));
+        }
+        matched.join("\n")
+    } else if is_html {
+        visible_text(Html::parse_document(body).root_element()).join("\n")
+    } else {
+        body.to_string()
+    };
+
+    if let Some(pattern) = regex {
+        let re = Regex::new(pattern).map_err(|e| format!("Invalid Regex: {}", e))?;
+        let extracted: Vec<&str> = re.captures_iter(&text)
+            .filter_map(|c| c.get(1).or_else(|| c.get(0)).map(|m| m.as_str()))
+            .collect();
+        if extracted.is_empty() {
+            return Err(format!("Regex '{}' Matched Nothing", pattern));
+        }
+        text = extracted.join("\n");
+    }
+
+    for pattern in ignore {
+        let re = Regex::new(pattern).map_err(|e| format!("Invalid Ignore Regex: {}", e))?;
+        text = re.replace_all(&text, "").into_owned();
+    }
+
+    Ok(text.lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+pub fn digest(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Changed lines between two snapshots, capped for notification payloads.
+pub fn diff_excerpt(old: &str, new: &str) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let changes: Vec<String> = diff.iter_all_changes()
+        .filter_map(|c| match c.tag() {
+            ChangeTag::Delete => Some(format!("- {}", c.value().trim_end())),
+            ChangeTag::Insert => Some(format!("+ {}", c.value().trim_end())),
+            ChangeTag::Equal => None,
+        })
+        .collect();
+
+    let mut excerpt = String::new();
+    let mut shown = 0;
+    for line in &changes {
+        if shown == DIFF_LINES || excerpt.len() + line.len() + 1 > DIFF_CHARS {
+            break;
+        }
+        excerpt.push_str(line);
+        excerpt.push('\n');
+        shown += 1;
+    }
+    if shown < changes.len() {
+        excerpt.push_str(&format!("... {} more changed lines\n", changes.len() - shown));
+    }
+    excerpt
+}
+
+impl Monitor {
+    /// Fetches `url`, hashes its normalized content and compares it with the last snapshot for `key`.
+    pub async fn check_content(&self, key: &str, url: &str, client: &reqwest::Client, check: &CheckType) -> (ProbeOutcome, Option<ContentChange>) {
+        let CheckType::ContentHash { selector, regex, ignore, timeout_ms, .. } = check else {
+            return (ProbeOutcome::new(Status::Unknown, "Not a content check"), None);
+        };
+
+        let start = std::time::Instant::now();
+        let response = match client.get(url).timeout(Duration::from_millis(*timeout_ms)).send().await {
+            Ok(r) => r,
+            Err(e) if e.is_timeout() => return (ProbeOutcome::new(Status::Down, "Request Timeout"), None),
+            Err(e) => return (ProbeOutcome::new(Status::Down, format!("Request error: {}", e)), None),
+        };
+        let status_code = response.status();
+        if !status_code.is_success() {
+            return (ProbeOutcome::new(Status::Down, format!("Status Code {}", status_code.as_u16())), None);
+        }
+        let is_html = response.headers().get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("html"));
+        let body = match response.text().await {
+            Ok(b) => b,
+            Err(e) => return (ProbeOutcome::new(Status::Down, format!("Body read error: {}", e)), None),
+        };
+        let latency = start.elapsed().as_secs_f64() * 1000.0;
+
+        let text = match normalize(&body, is_html, selector.as_deref(), regex.as_deref(), ignore) {
+            Ok(t) => t,
+            Err(e) => {
+                let mut outcome = ProbeOutcome::new(Status::Degraded, e);
+                outcome.latency_ms = Some(latency);
+                return (outcome, None);
+            }
+        };
+        let hash = digest(&text);
+
+        let previous = match &self.redis {
+            Some(redis) => redis.fetch_content_snapshot(key).await.ok().flatten(),
+            None => None,
+        };
+        let previous = match previous {
+            Some(p) => Some(p),
+            None => self.state.lock().await.content_snapshots.get(key).cloned(),
+        };
+
+        let (message, change, changed_at) = match &previous {
+            None => (format!("Baseline Recorded ({})", &hash[..12]), None, None),
+            Some(p) if p.hash == hash => {
+                let since = p.changed_at.map_or(String::new(), |t| format!(", last change {}", t.format("%Y-%m-%d %H:%M UTC")));
+                (format!("Content Unchanged ({}{})", &hash[..12], since), None, p.changed_at)
+            }
+            Some(p) => {
+                let change = ContentChange {
+                    previous_hash: p.hash.clone(),
+                    hash: hash.clone(),
+                    diff: diff_excerpt(&p.text, &text),
+                };
+                (format!("Content Changed ({} \u{2192} {})", &p.hash[..12], &hash[..12]), Some(change), Some(Utc::now()))
+            }
+        };
+
+        let snapshot = ContentSnapshot { hash, text, changed_at };
+        if let Some(redis) = &self.redis {
+            let _ = redis.push_content_snapshot(key, &snapshot).await;
+        }
+        let lines = snapshot.text.lines().count();
+        let bytes = snapshot.text.len();
+        self.state.lock().await.content_snapshots.insert(key.to_string(), snapshot);
+
+        let mut outcome = ProbeOutcome::new(Status::Up, message)
+            .metric("content_bytes", bytes as f64)
+            .metric("content_lines", lines as f64);
+        outcome.latency_ms = Some(latency);
+        (outcome, change)
+    }
+}
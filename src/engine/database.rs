@@ -0,0 +1,262 @@
+use std::time::{Duration, Instant};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::tls::MakeTlsConnect;
+use crate::config::{CheckType, PostgresTls, SourceBinding};
+use crate::engine::{net, tls};
+use crate::models::{ProbeOutcome, Status};
+
+const MYSQL_ACCESS_DENIED: u16 = 1045;
+const MYSQL_DB_ACCESS_DENIED: u16 = 1044;
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Performs a native protocol handshake against a database target.
+///
+/// MySQL and Redis open their own sockets, so only Postgres honours `bind`; config validation rejects bound MySQL and Redis checks.
+pub async fn check_database(address: &str, check: &CheckType, bind: &SourceBinding) -> ProbeOutcome {
+    let (timeout_ms, probe) = match check {
+        CheckType::Postgres { port, user, password, database, query, role, tls, timeout_ms, .. } => (
+            *timeout_ms,
+            Box::pin(check_postgres(address, *port, user, password.as_deref(), database.as_deref(), query.as_deref(), role.as_deref(), *tls, bind))
+                as std::pin::Pin<Box<dyn std::future::Future<Output = ProbeOutcome> + Send + '_>>,
+        ),
+        CheckType::MySql { port, user, password, database, query, timeout_ms, .. } => (
+            *timeout_ms,
+            Box::pin(check_mysql(address, *port, user, password.as_deref(), database.as_deref(), query.as_deref())) as _,
+        ),
+        CheckType::Redis { port, username, password, role, timeout_ms, .. } => (
+            *timeout_ms,
+            Box::pin(check_redis(address, *port, username.as_deref(), password.as_deref(), role.as_deref())) as _,
+        ),
+        _ => return ProbeOutcome::new(Status::Unknown, "Not a database check"),
+    };
+
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), probe).await {
+        Ok(outcome) => outcome,
+        Err(_) => ProbeOutcome::new(Status::Down, "Request Timeout"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn check_postgres(address: &str, port: u16, user: &str, password: Option<&str>, database: Option<&str>, query: Option<&str>, role: Option<&str>, tls_mode: PostgresTls, bind: &SourceBinding) -> ProbeOutcome {
+    let mut config = tokio_postgres::Config::new();
+    config.user(user).application_name("spectra-monitor");
+    if let Some(p) = password {
+        config.password(p);
+    }
+    if let Some(db) = database {
+        config.dbname(db);
+    }
+
+    config.ssl_mode(match tls_mode {
+        PostgresTls::Disable => SslMode::Disable,
+        PostgresTls::Prefer => SslMode::Prefer,
+        PostgresTls::Require => SslMode::Require,
+    });
+
+    let start = Instant::now();
+    // The socket is opened here rather than by tokio-postgres so it leaves through the source binding.
+    let stream = match net::connect_tcp(&net::host_port(address, port), bind).await {
+        Ok(s) => s,
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Connection Failed: {}", e)),
+    };
+    let mut connector = tokio_postgres_rustls::MakeRustlsConnect::new((*tls::client_config()).clone());
+    let connector = match MakeTlsConnect::<tokio::net::TcpStream>::make_tls_connect(&mut connector, address) {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("TLS Setup Failed: {}", e)),
+    };
+    let (client, connection) = match config.connect_raw(stream, connector).await {
+        Ok(c) => c,
+        Err(e) => {
+            let msg = match e.code() {
+                Some(code) if *code == SqlState::INVALID_PASSWORD || *code == SqlState::INVALID_AUTHORIZATION_SPECIFICATION => {
+                    format!("Authentication Rejected: {}", e.as_db_error().map_or(e.to_string(), |d| d.message().to_string()))
+                }
+                Some(code) if *code == SqlState::CANNOT_CONNECT_NOW => {
+                    "Server Unavailable (Starting Up or Recovering)".into()
+                }
+                _ => match std::error::Error::source(&e) {
+                    Some(cause) => format!("Connection Failed: {}: {}", e, cause),
+                    None => format!("Connection Failed: {}", e),
+                },
+            };
+            return ProbeOutcome::new(Status::Down, msg);
+        }
+    };
+    let connect_ms = elapsed_ms(start);
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let in_recovery = match client.query_one("SELECT pg_is_in_recovery()", &[]).await {
+        Ok(row) => row.get::<_, bool>(0),
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Recovery Probe Failed: {}", e)),
+    };
+    let actual_role = if in_recovery { "replica" } else { "primary" };
+
+    let expected = role.map(normalize_role);
+    match expected {
+        Some(exp) if exp != actual_role => {
+            return ProbeOutcome::new(Status::Down, format!("Role Mismatch: {}, expected {}", actual_role, exp))
+                .metric("connect_ms", connect_ms);
+        }
+        None if in_recovery => {
+            return ProbeOutcome::new(Status::Down, "Server In Recovery (set role \"replica\" for standbys)")
+                .metric("connect_ms", connect_ms);
+        }
+        _ => {}
+    }
+
+    let mut outcome = ProbeOutcome::new(Status::Up, format!("PostgreSQL Handshake OK ({})", actual_role))
+        .metric("connect_ms", connect_ms);
+    outcome.latency_ms = Some(connect_ms);
+
+    if let Some(q) = query {
+        let q_start = Instant::now();
+        if let Err(e) = client.simple_query(q).await {
+            return ProbeOutcome::new(Status::Down, format!("Query Failed: {}", e))
+                .metric("connect_ms", connect_ms);
+        }
+        let query_ms = elapsed_ms(q_start);
+        outcome = outcome.metric("query_ms", query_ms);
+        outcome.latency_ms = Some(query_ms);
+        outcome.message = format!("PostgreSQL Query OK ({})", actual_role);
+    }
+    outcome
+}
+
+async fn check_mysql(address: &str, port: u16, user: &str, password: Option<&str>, database: Option<&str>, query: Option<&str>) -> ProbeOutcome {
+    use mysql_async::prelude::Queryable;
+
+    let opts = mysql_async::OptsBuilder::default()
+        .ip_or_hostname(address)
+        .tcp_port(port)
+        .prefer_socket(false)
+        .user(Some(user))
+        .pass(password)
+        .db_name(database);
+
+    let start = Instant::now();
+    let mut conn = match mysql_async::Conn::new(opts).await {
+        Ok(c) => c,
+        Err(mysql_async::Error::Server(e)) if e.code == MYSQL_ACCESS_DENIED || e.code == MYSQL_DB_ACCESS_DENIED => {
+            return ProbeOutcome::new(Status::Down, format!("Authentication Rejected: {}", e.message));
+        }
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Connection Failed: {}", e)),
+    };
+    let connect_ms = elapsed_ms(start);
+
+    let mut outcome = ProbeOutcome::new(Status::Up, format!("MySQL Handshake OK (Server {})", version_string(conn.server_version())))
+        .metric("connect_ms", connect_ms);
+    outcome.latency_ms = Some(connect_ms);
+
+    if let Some(q) = query {
+        let q_start = Instant::now();
+        if let Err(e) = conn.query_drop(q).await {
+            let _ = conn.disconnect().await;
+            return ProbeOutcome::new(Status::Down, format!("Query Failed: {}", e))
+                .metric("connect_ms", connect_ms);
+        }
+        let query_ms = elapsed_ms(q_start);
+        outcome = outcome.metric("query_ms", query_ms);
+        outcome.latency_ms = Some(query_ms);
+        outcome.message = "MySQL Query OK".into();
+    }
+
+    let _ = conn.disconnect().await;
+    outcome
+}
+
+fn version_string((major, minor, patch): (u16, u16, u16)) -> String {
+    format!("{}.{}.{}", major, minor, patch)
+}
+
+async fn check_redis(address: &str, port: u16, username: Option<&str>, password: Option<&str>, role: Option<&str>) -> ProbeOutcome {
+    let info = redis::ConnectionInfo {
+        addr: redis::ConnectionAddr::Tcp(address.to_string(), port),
+        redis: redis::RedisConnectionInfo {
+            username: username.map(String::from),
+            password: password.map(String::from),
+            ..Default::default()
+        },
+    };
+    let client = match redis::Client::open(info) {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Redis Client Fault: {}", e)),
+    };
+
+    let start = Instant::now();
+    let mut conn = match client.get_multiplexed_async_connection().await {
+        Ok(c) => c,
+        Err(e) if e.kind() == redis::ErrorKind::AuthenticationFailed => {
+            return ProbeOutcome::new(Status::Down, format!("Authentication Rejected: {}", e));
+        }
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Connection Failed: {}", e)),
+    };
+    let connect_ms = elapsed_ms(start);
+
+    let ping_start = Instant::now();
+    let pong: redis::RedisResult<String> = redis::cmd("PING").query_async(&mut conn).await;
+    let ping_ms = elapsed_ms(ping_start);
+    match pong {
+        Ok(p) if p == "PONG" => {}
+        Ok(p) => return ProbeOutcome::new(Status::Down, format!("Unexpected PING Reply: {}", p)),
+        Err(e) => {
+            let msg = match e.kind() {
+                redis::ErrorKind::AuthenticationFailed => format!("Authentication Rejected: {}", e),
+                _ => format!("PING Failed: {}", e),
+            };
+            return ProbeOutcome::new(Status::Down, msg);
+        }
+    }
+
+    let replication: String = match redis::cmd("INFO").arg("replication").query_async(&mut conn).await {
+        Ok(r) => r,
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("INFO Failed: {}", e)),
+    };
+    let field = |name: &str| {
+        replication.lines()
+            .find_map(|l| l.strip_prefix(name).and_then(|rest| rest.strip_prefix(':')))
+            .map(|v| v.trim().to_string())
+    };
+
+    let actual_role = match field("role").as_deref() {
+        Some("slave") => "replica",
+        Some("master") => "primary",
+        Some(other) => return ProbeOutcome::new(Status::Unknown, format!("Unrecognized Redis Role: {}", other)),
+        None => return ProbeOutcome::new(Status::Unknown, "Redis Role Missing From INFO"),
+    };
+
+    let mut outcome = ProbeOutcome::new(Status::Up, format!("Redis PONG ({})", actual_role))
+        .metric("connect_ms", connect_ms)
+        .metric("ping_ms", ping_ms);
+    outcome.latency_ms = Some(ping_ms);
+
+    if let Some(slaves) = field("connected_slaves").and_then(|v| v.parse::<f64>().ok()) {
+        outcome = outcome.metric("connected_replicas", slaves);
+    }
+
+    if let Some(exp) = role.map(normalize_role)
+        && exp != actual_role
+    {
+        outcome.health = Status::Down;
+        outcome.message = format!("Role Mismatch: {}, expected {}", actual_role, exp);
+        return outcome;
+    }
+
+    if actual_role == "replica" && field("master_link_status").as_deref() != Some("up") {
+        outcome.health = Status::Down;
+        outcome.message = "Replica Link To Primary Down".into();
+    }
+    outcome
+}
+
+fn normalize_role(role: &str) -> &'static str {
+    match role.to_lowercase().as_str() {
+        "replica" | "slave" | "standby" => "replica",
+        _ => "primary",
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use regex::Regex;
+use reqwest::cookie::{CookieStore, Jar};
+use serde_json::Value;
+use crate::config::{FlowExtract, FlowStep};
+use crate::engine::net::{self, Route};
+use crate::models::{ProbeOutcome, Status};
+
+/// Replaces every `{{name}}` in `template` with its variable, failing on unknown names.
+fn substitute(template: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| format!("Unclosed '{{{{' in '{}'", template))?;
+        let name = after[..end].trim();
+        out.push_str(vars.get(name).ok_or_else(|| format!("Undefined Variable '{}'", name))?);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Walks a dotted path such as `data.items[0].id` (a leading `$.` is allowed).
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let normalized = path.trim_start_matches('$').replace('[', ".").replace(']', "");
+    normalized.split('.')
+        .filter(|s| !s.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => current.get(segment),
+        })
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn cookie_value(jar: &Jar, url: &reqwest::Url, name: &str) -> Option<String> {
+    let header = jar.cookies(url)?;
+    header.to_str().ok()?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+struct StepResponse {
+    url: reqwest::Url,
+    status: u16,
+    headers: reqwest::header::HeaderMap,
+    body: String,
+}
+
+fn extract(rule: &FlowExtract, response: &StepResponse, json: &mut Option<Option<Value>>, jar: &Jar) -> Result<String, String> {
+    if let Some(path) = &rule.json {
+        let parsed = json.get_or_insert_with(|| serde_json::from_str(&response.body).ok());
+        let doc = parsed.as_ref().ok_or("response is not JSON")?;
+        return json_path(doc, path).map(json_text).ok_or_else(|| format!("JSON path '{}' not found", path));
+    }
+    if let Some(pattern) = &rule.regex {
+        let re = Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))?;
+        let caps = re.captures(&response.body).ok_or_else(|| format!("regex '{}' did not match", pattern))?;
+        return Ok(caps.get(1).or_else(|| caps.get(0)).map_or("", |m| m.as_str()).to_string());
+    }
+    if let Some(name) = &rule.cookie {
+        return cookie_value(jar, &response.url, name).ok_or_else(|| format!("cookie '{}' not set", name));
+    }
+    if let Some(name) = &rule.header {
+        return response.headers.get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| format!("header '{}' missing", name));
+    }
+    Err("no json, regex, cookie or header source".into())
+}
+
+async fn run_step(client: &reqwest::Client, base: &reqwest::Url, step: &FlowStep, vars: &HashMap<String, String>, timeout: Duration) -> Result<StepResponse, String> {
+    let url = substitute(&step.url, vars)?;
+    let url = base.join(&url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    let method = reqwest::Method::from_bytes(step.method.to_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid Method '{}'", step.method))?;
+
+    let mut request = client.request(method, url).timeout(timeout);
+    for (name, value) in &step.headers {
+        request = request.header(name, substitute(value, vars)?);
+    }
+    if let Some(body) = &step.body {
+        request = request.body(substitute(body, vars)?);
+    }
+
+    let response = request.send().await.map_err(|e| {
+        if e.is_timeout() { "Request Timeout".to_string() } else { format!("Request error: {}", e) }
+    })?;
+    let url = response.url().clone();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let body = response.text().await.map_err(|e| format!("Body read error: {}", e))?;
+    Ok(StepResponse { url, status, headers, body })
+}
+
+fn assert_step(step: &FlowStep, response: &StepResponse, json: &mut Option<Option<Value>>, vars: &HashMap<String, String>) -> Result<(), String> {
+    match step.expect_status {
+        Some(expected) if response.status != expected => {
+            return Err(format!("Status Code {}, expected {}", response.status, expected));
+        }
+        None if !(200..300).contains(&response.status) => {
+            return Err(format!("Status Code {}", response.status));
+        }
+        _ => {}
+    }
+    if let Some(needle) = &step.contains {
+        let needle = substitute(needle, vars)?;
+        if !response.body.contains(&needle) {
+            return Err(format!("Body missing '{}'", needle));
+        }
+    }
+    if !step.expect_json.is_empty() {
+        let parsed = json.get_or_insert_with(|| serde_json::from_str(&response.body).ok());
+        let doc = parsed.as_ref().ok_or("Response is not JSON")?;
+        for (path, expected) in &step.expect_json {
+            match json_path(doc, path) {
+                Some(actual) if actual == expected => {}
+                Some(actual) => return Err(format!("JSON '{}' is {}, expected {}", path, actual, expected)),
+                None => return Err(format!("JSON '{}' not found", path)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the steps in order with one cookie jar, stopping at the first failed request or assertion.
+pub async fn run_flow(route: &Route, base: &str, steps: &[FlowStep], variables: &HashMap<String, String>, timeout_ms: u64) -> ProbeOutcome {
+    let base_url = match reqwest::Url::parse(base) {
+        Ok(u) => u,
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Invalid Base URL '{}': {}", base, e)),
+    };
+    let jar = Arc::new(Jar::default());
+    let client = match net::client_builder(route).and_then(|b| b.cookie_provider(Arc::clone(&jar)).build().map_err(|e| e.to_string())) {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Flow Setup Failed: {}", e)),
+    };
+
+    let mut vars = variables.clone();
+    vars.entry("base".into()).or_insert_with(|| base.trim_end_matches('/').to_string());
+    let timeout = Duration::from_millis(timeout_ms);
+    let total = steps.len();
+    let mut timings = Vec::with_capacity(total);
+    let mut metrics = Vec::with_capacity(total);
+    let flow_start = Instant::now();
+
+    for (idx, step) in steps.iter().enumerate() {
+        let start = Instant::now();
+        let outcome = run_step(&client, &base_url, step, &vars, timeout).await;
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+        let checked = outcome.and_then(|response| {
+            metrics.push((format!("step{:02}_ms", idx + 1), elapsed));
+            let mut json = None;
+            assert_step(step, &response, &mut json, &vars)?;
+            for rule in &step.extract {
+                let value = extract(rule, &response, &mut json, &jar)
+                    .map_err(|e| format!("Extract '{}' failed: {}", rule.var, e))?;
+                vars.insert(rule.var.clone(), value);
+            }
+            Ok(())
+        });
+
+        if let Err(reason) = checked {
+            let mut outcome = ProbeOutcome::new(Status::Down, format!("Step {}/{} '{}' Failed: {}", idx + 1, total, step.label(), reason))
+                .metric("failed_step", (idx + 1) as f64)
+                .metric("steps_passed", idx as f64);
+            for (name, value) in metrics {
+                outcome = outcome.metric(&name, value);
+            }
+            outcome.latency_ms = Some(flow_start.elapsed().as_secs_f64() * 1000.0);
+            return outcome;
+        }
+        timings.push(format!("{} {:.0}ms", step.label(), elapsed));
+    }
+
+    let mut outcome = ProbeOutcome::new(Status::Up, format!("Flow Completed: {}", timings.join(" \u{2192} ")))
+        .metric("steps_passed", total as f64);
+    for (name, value) in metrics {
+        outcome = outcome.metric(&name, value);
+    }
+    outcome.latency_ms = Some(flow_start.elapsed().as_secs_f64() * 1000.0);
+    outcome
+}
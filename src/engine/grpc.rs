@@ -0,0 +1,106 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tonic::transport::{ClientTlsConfig, Endpoint};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use crate::models::{ProbeOutcome, Status};
+
+/// Calls `grpc.health.v1.Health/Check` and maps `SERVING` to Up.
+pub async fn check_grpc(address: &str, port: u16, service: Option<&str>, tls: bool, timeout_ms: u64) -> ProbeOutcome {
+    let timeout = Duration::from_millis(timeout_ms);
+    let host = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V6(v6)) => format!("[{}]", v6),
+        _ => address.to_string(),
+    };
+    let uri = format!("{}://{}:{}", if tls { "https" } else { "http" }, host, port);
+
+    let mut endpoint = match Endpoint::from_shared(uri) {
+        Ok(e) => e.connect_timeout(timeout).timeout(timeout),
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Invalid gRPC Endpoint: {}", e)),
+    };
+    if tls {
+        let tls_config = ClientTlsConfig::new().with_webpki_roots().domain_name(address);
+        endpoint = match endpoint.tls_config(tls_config) {
+            Ok(e) => e,
+            Err(e) => return ProbeOutcome::new(Status::Unknown, format!("TLS Configuration Fault: {}", e)),
+        };
+    }
+
+    let start = Instant::now();
+    let channel = match endpoint.connect().await {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Down, format!("Channel Connect Failed: {}", error_chain(&e))),
+    };
+    let connect_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let mut client = HealthClient::new(channel);
+    let request = HealthCheckRequest { service: service.unwrap_or_default().to_string() };
+    let rpc_start = Instant::now();
+    let response = client.check(request).await;
+    let rpc_ms = rpc_start.elapsed().as_secs_f64() * 1000.0;
+
+    let status = match response {
+        Ok(r) => r.into_inner().status(),
+        Err(s) => {
+            let msg = match s.code() {
+                tonic::Code::Unimplemented => "Health Service Not Implemented".to_string(),
+                tonic::Code::NotFound => format!("Unknown Service '{}'", service.unwrap_or_default()),
+                tonic::Code::DeadlineExceeded => "Request Timeout".to_string(),
+                code => format!("RPC Failed ({:?}): {}", code, s.message()),
+            };
+            return ProbeOutcome::new(Status::Down, msg).metric("connect_ms", connect_ms);
+        }
+    };
+
+    let health = if status == ServingStatus::Serving { Status::Up } else { Status::Down };
+    let mut outcome = ProbeOutcome::new(health, format!("Health: {}", status.as_str_name()))
+        .metric("connect_ms", connect_ms)
+        .metric("rpc_ms", rpc_ms);
+    outcome.latency_ms = Some(rpc_ms);
+    outcome
+}
+
+/// Transport errors hide the useful cause behind a generic top-level message.
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut msg = err.to_string();
+    let mut source = err.source();
+    while let Some(s) = source {
+        let part = s.to_string();
+        if !msg.ends_with(&part) {
+            msg = format!("{}: {}", msg, part);
+        }
+        source = s.source();
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::Server;
+    use tonic::transport::server::TcpIncoming;
+
+    #[tokio::test]
+    async fn health_server_statuses() {
+        let (reporter, service) = tonic_health::server::health_reporter();
+        reporter.set_service_status("api", tonic_health::ServingStatus::Serving).await;
+        reporter.set_service_status("batch", tonic_health::ServingStatus::NotServing).await;
+
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let port = incoming.local_addr().unwrap().port();
+        tokio::spawn(Server::builder().add_service(service).serve_with_incoming(incoming));
+
+        let outcome = check_grpc("127.0.0.1", port, Some("api"), false, 2000).await;
+        assert_eq!(outcome.health, Status::Up);
+        assert_eq!(outcome.message, "Health: SERVING");
+
+        let outcome = check_grpc("127.0.0.1", port, Some("batch"), false, 2000).await;
+        assert_eq!(outcome.health, Status::Down);
+        assert_eq!(outcome.message, "Health: NOT_SERVING");
+
+        let outcome = check_grpc("127.0.0.1", port, Some("nope"), false, 2000).await;
+        assert_eq!(outcome.health, Status::Down);
+        assert_eq!(outcome.message, "Unknown Service 'nope'");
+    }
+}
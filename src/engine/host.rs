@@ -0,0 +1,395 @@
+use std::time::Duration;
+use crate::config::CheckType;
+use crate::models::{ProbeOutcome, Status};
+
+/// Grades a value where higher readings are worse.
+pub fn grade_above(value: f64, warn: Option<f64>, crit: Option<f64>) -> Status {
+    if crit.is_some_and(|c| value >= c) {
+        Status::Down
+    } else if warn.is_some_and(|w| value >= w) {
+        Status::Degraded
+    } else {
+        Status::Up
+    }
+}
+
+/// Grades a count where fewer is worse, e.g. running instances of a process.
+#[cfg(any(target_os = "linux", test))]
+fn grade_below(count: u32, warn_below: u32, crit_below: u32) -> Status {
+    if count < crit_below {
+        Status::Down
+    } else if count < warn_below {
+        Status::Degraded
+    } else {
+        Status::Up
+    }
+}
+
+pub fn worst(a: Status, b: Status) -> Status {
+    let rank = |s: Status| match s {
+        Status::Up => 0,
+        Status::Degraded => 1,
+        Status::Unknown | Status::Flapping => 2,
+        Status::Down => 3,
+    };
+    if rank(b) > rank(a) { b } else { a }
+}
+
+fn label(health: Status) -> &'static str {
+    match health {
+        Status::Up => "Nominal",
+        Status::Degraded => "Warning",
+        Status::Down => "Critical",
+        Status::Unknown | Status::Flapping => "Unknown",
+    }
+}
+
+pub async fn check_host(check: CheckType) -> ProbeOutcome {
+    let task = tokio::task::spawn_blocking(move || read_host(&check));
+    match tokio::time::timeout(Duration::from_secs(5), task).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => ProbeOutcome::new(Status::Unknown, format!("Host Probe Fault: {}", e)),
+        Err(_) => ProbeOutcome::new(Status::Unknown, "Host Probe Timeout"),
+    }
+}
+
+fn read_host(check: &CheckType) -> ProbeOutcome {
+    match check {
+        CheckType::Disk { mount, warn_pct, crit_pct, .. } => check_disk(mount, *warn_pct, *crit_pct),
+        CheckType::Memory { warn_pct, crit_pct, swap_warn_pct, swap_crit_pct, .. } => {
+            check_memory(*warn_pct, *crit_pct, *swap_warn_pct, *swap_crit_pct)
+        }
+        CheckType::Load { warn, crit, per_core, .. } => check_load(*warn, *crit, *per_core),
+        CheckType::Process { name, warn_below, crit_below, .. } => check_process(name, *warn_below, *crit_below),
+        CheckType::ListeningPort { port, warn_connections, crit_connections, .. } => {
+            check_listening_port(*port, *warn_connections, *crit_connections)
+        }
+        CheckType::FileAge { path_glob, max_age, min_size, .. } => check_file_age(path_glob, *max_age, *min_size),
+        _ => ProbeOutcome::new(Status::Unknown, "Not a host check"),
+    }
+}
+
+pub fn format_age(secs: f64) -> String {
+    if secs >= 86_400.0 {
+        format!("{:.1}d", secs / 86_400.0)
+    } else if secs >= 3_600.0 {
+        format!("{:.1}h", secs / 3_600.0)
+    } else if secs >= 60.0 {
+        format!("{:.0}m", secs / 60.0)
+    } else {
+        format!("{:.0}s", secs)
+    }
+}
+
+fn check_file_age(path_glob: &str, max_age: u64, min_size: Option<u64>) -> ProbeOutcome {
+    let paths = match glob::glob(path_glob) {
+        Ok(p) => p,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Invalid glob {}: {}", path_glob, e)),
+    };
+
+    let mut matches = 0u32;
+    let mut newest: Option<(std::path::PathBuf, std::time::SystemTime, u64)> = None;
+    for path in paths.flatten() {
+        let Ok(meta) = std::fs::metadata(&path) else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        matches += 1;
+        let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+        if newest.as_ref().is_none_or(|(_, m, _)| modified > *m) {
+            newest = Some((path, modified, meta.len()));
+        }
+    }
+
+    let Some((path, modified, size)) = newest else {
+        return ProbeOutcome::new(Status::Down, format!("No file matches {}", path_glob)).metric("matches", 0.0);
+    };
+    let age = modified.elapsed().map_or(0.0, |d| d.as_secs_f64());
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+
+    let (health, msg) = match min_size {
+        _ if age > max_age as f64 => {
+            (Status::Down, format!("Newest file {} is {} old (max {})", name, format_age(age), format_age(max_age as f64)))
+        }
+        Some(min) if size < min => (Status::Down, format!("Newest file {} is {} bytes (min {})", name, size, min)),
+        _ => (Status::Up, format!("Newest file {} is {} old, {} bytes", name, format_age(age), size)),
+    };
+    ProbeOutcome::new(health, msg)
+        .metric("age_secs", age)
+        .metric("size_bytes", size as f64)
+        .metric("matches", matches as f64)
+}
+
+#[cfg(unix)]
+fn check_disk(mount: &str, warn_pct: f64, crit_pct: f64) -> ProbeOutcome {
+    use std::ffi::CString;
+
+    let path = match CString::new(mount) {
+        Ok(p) => p,
+        Err(_) => return ProbeOutcome::new(Status::Unknown, format!("Invalid mount path {}", mount)),
+    };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        let err = std::io::Error::last_os_error();
+        return ProbeOutcome::new(Status::Down, format!("Mount {} unavailable: {}", mount, err));
+    }
+
+    let frsize = stat.f_frsize as f64;
+    let total = stat.f_blocks as f64 * frsize;
+    let free = stat.f_bfree as f64 * frsize;
+    let avail = stat.f_bavail as f64 * frsize;
+    let used = total - free;
+    // Match df: reserved blocks are excluded from the usable capacity.
+    let usable = used + avail;
+    let used_pct = if usable > 0.0 { used / usable * 100.0 } else { 0.0 };
+
+    let health = grade_above(used_pct, Some(warn_pct), Some(crit_pct));
+    ProbeOutcome::new(health, format!("Disk {}: {:.1}% used, {:.1} GiB free ({})",
+        mount, used_pct, avail / 1_073_741_824.0, label(health)))
+        .metric("used_pct", used_pct)
+        .metric("used_bytes", used)
+        .metric("avail_bytes", avail)
+        .metric("total_bytes", total)
+}
+
+#[cfg(not(unix))]
+fn check_disk(_mount: &str, _warn_pct: f64, _crit_pct: f64) -> ProbeOutcome {
+    unsupported()
+}
+
+#[cfg(target_os = "linux")]
+fn check_memory(warn_pct: f64, crit_pct: f64, swap_warn_pct: Option<f64>, swap_crit_pct: Option<f64>) -> ProbeOutcome {
+    let content = match std::fs::read_to_string("/proc/meminfo") {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Cannot read /proc/meminfo: {}", e)),
+    };
+
+    let field = |name: &str| -> Option<f64> {
+        content.lines()
+            .find(|l| l.starts_with(name) && l[name.len()..].starts_with(':'))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|kib| kib * 1024.0)
+    };
+
+    let (Some(total), Some(available)) = (field("MemTotal"), field("MemAvailable")) else {
+        return ProbeOutcome::new(Status::Unknown, "Memory counters missing from /proc/meminfo");
+    };
+    let swap_total = field("SwapTotal").unwrap_or(0.0);
+    let swap_free = field("SwapFree").unwrap_or(0.0);
+
+    let mem_pct = if total > 0.0 { (total - available) / total * 100.0 } else { 0.0 };
+    let swap_pct = if swap_total > 0.0 { (swap_total - swap_free) / swap_total * 100.0 } else { 0.0 };
+
+    let health = worst(
+        grade_above(mem_pct, Some(warn_pct), Some(crit_pct)),
+        grade_above(swap_pct, swap_warn_pct, swap_crit_pct),
+    );
+    ProbeOutcome::new(health, format!("Memory {:.1}% used, Swap {:.1}% used ({})", mem_pct, swap_pct, label(health)))
+        .metric("mem_used_pct", mem_pct)
+        .metric("mem_available_bytes", available)
+        .metric("mem_total_bytes", total)
+        .metric("swap_used_pct", swap_pct)
+        .metric("swap_total_bytes", swap_total)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_memory(_warn_pct: f64, _crit_pct: f64, _swap_warn_pct: Option<f64>, _swap_crit_pct: Option<f64>) -> ProbeOutcome {
+    unsupported()
+}
+
+#[cfg(target_os = "linux")]
+fn check_load(warn: f64, crit: f64, per_core: bool) -> ProbeOutcome {
+    let content = match std::fs::read_to_string("/proc/loadavg") {
+        Ok(c) => c,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Cannot read /proc/loadavg: {}", e)),
+    };
+    let loads: Vec<f64> = content.split_whitespace().take(3).filter_map(|v| v.parse().ok()).collect();
+    if loads.len() < 3 {
+        return ProbeOutcome::new(Status::Unknown, "Malformed /proc/loadavg");
+    }
+
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64;
+    let scale = if per_core { cores } else { 1.0 };
+    let graded = loads[0] / scale;
+
+    let health = grade_above(graded, Some(warn), Some(crit));
+    ProbeOutcome::new(health, format!("Load {:.2} {:.2} {:.2} on {} cores ({})",
+        loads[0], loads[1], loads[2], cores, label(health)))
+        .metric("load1", loads[0])
+        .metric("load5", loads[1])
+        .metric("load15", loads[2])
+        .metric("cores", cores)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_load(_warn: f64, _crit: f64, _per_core: bool) -> ProbeOutcome {
+    unsupported()
+}
+
+#[cfg(target_os = "linux")]
+fn check_process(name: &str, warn_below: u32, crit_below: u32) -> ProbeOutcome {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(e) => e,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Cannot read /proc: {}", e)),
+    };
+
+    let mut count = 0u32;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        if !file_name.to_string_lossy().bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let path = entry.path();
+        let comm = std::fs::read_to_string(path.join("comm")).unwrap_or_default();
+        let cmdline = || std::fs::read(path.join("cmdline")).unwrap_or_default();
+        if process_matches(name, &comm, cmdline) {
+            count += 1;
+        }
+    }
+
+    let health = grade_below(count, warn_below, crit_below);
+    let msg = if count == 0 {
+        format!("Process {} not running", name)
+    } else {
+        format!("Process {}: {} instance(s) alive ({})", name, count, label(health))
+    };
+    ProbeOutcome::new(health, msg).metric("instances", count as f64)
+}
+
+/// Whether a process with `comm` and NUL-separated `cmdline` is `name`.
+#[cfg(any(target_os = "linux", test))]
+fn process_matches(name: &str, comm: &str, cmdline: impl FnOnce() -> Vec<u8>) -> bool {
+    if comm.trim_end() == name {
+        return true;
+    }
+    // comm is truncated to 15 bytes, so fall back to argv[0] for longer names.
+    let raw = cmdline();
+    let argv0 = String::from_utf8_lossy(raw.split(|b| *b == 0).next().unwrap_or_default());
+    argv0.rsplit('/').next() == Some(name)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_process(_name: &str, _warn_below: u32, _crit_below: u32) -> ProbeOutcome {
+    unsupported()
+}
+
+#[cfg(target_os = "linux")]
+fn check_listening_port(port: u16, warn_connections: Option<u32>, crit_connections: Option<u32>) -> ProbeOutcome {
+    let mut listening = false;
+    let mut established = 0u32;
+    let mut readable = false;
+
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = std::fs::read_to_string(table) else { continue };
+        readable = true;
+        let (listen, conns) = scan_socket_table(&content, port);
+        listening |= listen;
+        established += conns;
+    }
+
+    if !readable {
+        return ProbeOutcome::new(Status::Unknown, "Cannot read /proc/net/tcp");
+    }
+    if !listening {
+        return ProbeOutcome::new(Status::Down, format!("Port {} not listening", port))
+            .metric("connections", established as f64);
+    }
+
+    let health = grade_above(established as f64, warn_connections.map(f64::from), crit_connections.map(f64::from));
+    ProbeOutcome::new(health, format!("Port {} listening, {} established ({})", port, established, label(health)))
+        .metric("connections", established as f64)
+}
+
+/// Whether `port` is listening in a `/proc/net/tcp` table, and how many connections to it are established.
+#[cfg(any(target_os = "linux", test))]
+fn scan_socket_table(content: &str, port: u16) -> (bool, u32) {
+    const TCP_ESTABLISHED: &str = "01";
+    const TCP_LISTEN: &str = "0A";
+
+    let mut listening = false;
+    let mut established = 0u32;
+    for line in content.lines().skip(1) {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 4 {
+            continue;
+        }
+        let local_port = cols[1].rsplit(':').next().and_then(|p| u16::from_str_radix(p, 16).ok());
+        if local_port != Some(port) {
+            continue;
+        }
+        match cols[3] {
+            TCP_LISTEN => listening = true,
+            TCP_ESTABLISHED => established += 1,
+            _ => {}
+        }
+    }
+    (listening, established)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_listening_port(_port: u16, _warn_connections: Option<u32>, _crit_connections: Option<u32>) -> ProbeOutcome {
+    unsupported()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> ProbeOutcome {
+    ProbeOutcome::new(Status::Unknown, "Host telemetry unavailable on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_is_worse() {
+        assert_eq!(grade_above(79.9, Some(80.0), Some(90.0)), Status::Up);
+        assert_eq!(grade_above(80.0, Some(80.0), Some(90.0)), Status::Degraded);
+        assert_eq!(grade_above(90.0, Some(80.0), Some(90.0)), Status::Down);
+        assert_eq!(grade_above(95.0, Some(80.0), None), Status::Degraded);
+        assert_eq!(grade_above(95.0, None, Some(99.0)), Status::Up);
+        assert_eq!(grade_above(1e9, None, None), Status::Up);
+    }
+
+    #[test]
+    fn fewer_is_worse() {
+        assert_eq!(grade_below(0, 1, 1), Status::Down);
+        assert_eq!(grade_below(1, 1, 1), Status::Up);
+        assert_eq!(grade_below(1, 2, 1), Status::Degraded);
+        assert_eq!(grade_below(0, 2, 1), Status::Down);
+        assert_eq!(grade_below(3, 2, 1), Status::Up);
+    }
+
+    #[test]
+    fn worst_status_wins() {
+        assert_eq!(worst(Status::Up, Status::Degraded), Status::Degraded);
+        assert_eq!(worst(Status::Down, Status::Unknown), Status::Down);
+        assert_eq!(worst(Status::Degraded, Status::Unknown), Status::Unknown);
+        assert_eq!(worst(Status::Up, Status::Up), Status::Up);
+    }
+
+    #[test]
+    fn process_names() {
+        let no_cmdline = Vec::new;
+        assert!(process_matches("nginx", "nginx\n", no_cmdline));
+        assert!(!process_matches("nginx", "nginx-proxy\n", no_cmdline));
+        // Long names are cut to 15 bytes in comm but intact in argv[0].
+        let cmdline = || b"/usr/local/bin/prometheus-node-exporter\0--web.listen-address=:9100\0".to_vec();
+        assert!(process_matches("prometheus-node-exporter", "prometheus-node", cmdline));
+        assert!(!process_matches("exporter", "prometheus-node", cmdline));
+        assert!(!process_matches("sshd", "", no_cmdline));
+    }
+
+    #[test]
+    fn socket_table() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue\n\
+            0: 00000000:1F90 00000000:0000 0A 00000000:00000000\n\
+            1: 0100007F:1F90 0100007F:C350 01 00000000:00000000\n\
+            2: 0100007F:1F90 0100007F:C351 01 00000000:00000000\n\
+            3: 0100007F:1F90 0100007F:C352 06 00000000:00000000\n\
+            4: 0100007F:0016 0100007F:C353 01 00000000:00000000\n";
+        assert_eq!(scan_socket_table(table, 8080), (true, 2));
+        assert_eq!(scan_socket_table(table, 22), (false, 1));
+        assert_eq!(scan_socket_table(table, 443), (false, 0));
+    }
+}
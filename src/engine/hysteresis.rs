@@ -0,0 +1,118 @@
+use crate::config::FlapConfig;
+use crate::models::{Status, TransitionTracker};
+
+/// How many results a state must hold for before it is confirmed, and when a check counts as flapping.
+pub struct Policy<'a> {
+    pub fail_after: u32,
+    pub recover_after: u32,
+    pub flap: Option<&'a FlapConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    None,
+    /// A new state held for its required streak; `from` is `None` for the first one.
+    Confirmed { from: Option<Status>, to: Status },
+    FlapStarted { from: Option<Status> },
+    /// Flapping stopped and the latest result becomes the confirmed state.
+    FlapEnded { to: Status },
+}
+
+/// Share of the window's results that differed from the one before, in percent.
+pub fn flap_pct(tracker: &TransitionTracker, flap: &FlapConfig) -> f64 {
+    let changes = tracker.changes.iter().filter(|c| **c).count();
+    changes as f64 * 100.0 / (flap.window.max(2) - 1) as f64
+}
+
+/// Feeds one result into the tracker and reports whether its alerting state moved.
+pub fn observe(tracker: &mut TransitionTracker, health: Status, policy: &Policy) -> Transition {
+    if let Some(last) = tracker.last {
+        tracker.changes.push_back(last != health);
+    }
+    tracker.last = Some(health);
+    let capacity = policy.flap.map_or(0, |f| f.window.max(2) - 1);
+    while tracker.changes.len() > capacity {
+        tracker.changes.pop_front();
+    }
+
+    if tracker.candidate == Some(health) {
+        tracker.streak = tracker.streak.saturating_add(1);
+    } else {
+        tracker.candidate = Some(health);
+        tracker.streak = 1;
+    }
+
+    if let Some(flap) = policy.flap {
+        let pct = flap_pct(tracker, flap);
+        if !tracker.flapping && pct >= flap.high_pct {
+            tracker.flapping = true;
+            return Transition::FlapStarted { from: tracker.confirmed };
+        }
+        if tracker.flapping {
+            if pct >= flap.low_pct {
+                return Transition::None;
+            }
+            tracker.flapping = false;
+            tracker.confirmed = Some(health);
+            return Transition::FlapEnded { to: health };
+        }
+    }
+
+    let needed = if health == Status::Up { policy.recover_after } else { policy.fail_after };
+    if tracker.streak >= needed.max(1) && tracker.confirmed != Some(health) {
+        let from = tracker.confirmed.replace(health);
+        return Transition::Confirmed { from, to: health };
+    }
+    Transition::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(tracker: &mut TransitionTracker, policy: &Policy, health: Status, times: usize) -> Vec<Transition> {
+        (0..times).map(|_| observe(tracker, health, policy)).collect()
+    }
+
+    #[test]
+    fn failures_and_recoveries_need_a_streak() {
+        let policy = Policy { fail_after: 3, recover_after: 2, flap: None };
+        let mut tracker = TransitionTracker::default();
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::None);
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::Confirmed { from: None, to: Status::Up });
+
+        assert_eq!(feed(&mut tracker, &policy, Status::Down, 2), [Transition::None; 2]);
+        assert_eq!(tracker.confirmed, Some(Status::Up));
+        assert_eq!(observe(&mut tracker, Status::Down, &policy), Transition::Confirmed { from: Some(Status::Up), to: Status::Down });
+
+        // A single Up resets the streak without recovering.
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::None);
+        assert_eq!(observe(&mut tracker, Status::Down, &policy), Transition::None);
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::None);
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::Confirmed { from: Some(Status::Down), to: Status::Up });
+    }
+
+    #[test]
+    fn flapping_starts_at_high_and_ends_below_low() {
+        let flap = FlapConfig { window: 11, high_pct: 50.0, low_pct: 25.0 };
+        let policy = Policy { fail_after: 1, recover_after: 1, flap: Some(&flap) };
+        let mut tracker = TransitionTracker::default();
+
+        let alternating = [Status::Up, Status::Down].into_iter().cycle().take(11);
+        let transitions: Vec<Transition> = alternating.map(|h| observe(&mut tracker, h, &policy)).collect();
+        // Four changes (40%) are still alerted on; the fifth reaches 50%.
+        assert!(transitions[1..5].iter().all(|t| matches!(t, Transition::Confirmed { .. })));
+        assert_eq!(transitions[5], Transition::FlapStarted { from: Some(Status::Up) });
+        assert_eq!(transitions[6..], [Transition::None; 5]);
+        assert_eq!(flap_pct(&tracker, &flap), 100.0);
+
+        // Between low and high the check keeps flapping.
+        assert_eq!(feed(&mut tracker, &policy, Status::Up, 7), [Transition::None; 7]);
+        assert_eq!(flap_pct(&tracker, &flap), 30.0);
+        assert!(tracker.flapping);
+        assert_eq!(observe(&mut tracker, Status::Up, &policy), Transition::FlapEnded { to: Status::Up });
+        assert_eq!(flap_pct(&tracker, &flap), 20.0);
+
+        assert_eq!(observe(&mut tracker, Status::Down, &policy), Transition::Confirmed { from: Some(Status::Up), to: Status::Down });
+    }
+}
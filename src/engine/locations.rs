@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use tracing::warn;
+use crate::config::StatusPolicy;
+use crate::engine::Monitor;
+use crate::models::{CheckResult, NodeVerdict, Status};
+
+/// Separates a check key from the node in per-location keys; `@` already marks a source binding.
+const NODE_SEPARATOR: &str = "#node:";
+
+/// Key of one node's result for an `all_nodes` check.
+pub fn location_key(key: &str, node: &str) -> String {
+    format!("{}{}{}", key, NODE_SEPARATOR, node)
+}
+
+/// Key of the check a per-location key belongs to.
+pub fn base_key(key: &str) -> Option<&str> {
+    key.split_once(NODE_SEPARATOR).map(|(base, _)| base)
+}
+
+/// Folds per-location results into one result graded by `policy`.
+pub fn combine(mut base: CheckResult, policy: StatusPolicy, locations: &[CheckResult]) -> CheckResult {
+    let total = locations.len();
+    let up = locations.iter().filter(|l| l.status).count();
+    let passes = match policy {
+        StatusPolicy::Any => up > 0,
+        StatusPolicy::All => up == total,
+        StatusPolicy::Majority => up * 2 > total,
+    };
+    let health = if !passes {
+        Status::Down
+    } else if locations.iter().all(|l| l.health == Status::Up) {
+        Status::Up
+    } else {
+        Status::Degraded
+    };
+    base.set_health(health);
+    base.timestamp = Utc::now();
+
+    let latencies: Vec<f64> = locations.iter().filter(|l| l.status).filter_map(|l| l.latency_ms).collect();
+    base.latency_ms = (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+
+    let failing: Vec<String> = locations.iter()
+        .filter(|l| !l.status)
+        .map(|l| format!("{}: {}", l.location.as_deref().unwrap_or("?"), l.message))
+        .collect();
+    base.message = if failing.is_empty() {
+        format!("{}/{} Locations Up", up, total)
+    } else {
+        format!("{}/{} Locations Up ({})", up, total, failing.join("; "))
+    };
+    base.metrics.insert("locations".into(), total as f64);
+    base.metrics.insert("locations_up".into(), up as f64);
+    base.locations = locations.iter()
+        .map(|l| NodeVerdict::of(l.location.as_deref().unwrap_or("?"), l))
+        .collect();
+    base
+}
+
+impl Monitor {
+    /// Stores this node's result for an `all_nodes` check; it is alerted on only through the combined result.
+    pub async fn record_location(&self, result: CheckResult) {
+        let key = format!("{}-{}-{}-{}", result.server_name, result.parent_address, result.target_address, result.check_type);
+        let key = location_key(&key, result.location.as_deref().unwrap_or_default());
+        if let Some(redis) = &self.redis {
+            let _ = redis.push_result(&key, &result).await;
+        }
+        self.state.lock().await.last_results.insert(key, result);
+    }
+
+    /// Combines the fresh per-location results of each target into the result that is alerted on.
+    pub async fn combine_locations(self: &Arc<Self>, targets: Vec<(CheckResult, StatusPolicy)>, max_age: Duration) {
+        let Some(redis) = &self.redis else { return };
+        if targets.is_empty() {
+            return;
+        }
+        let all = match redis.fetch_all_results().await {
+            Ok(all) => all,
+            Err(e) => {
+                warn!("[LOCATIONS] Failed to fetch per-location results: {}", e);
+                return;
+            }
+        };
+
+        let cutoff = Utc::now() - max_age;
+        for (base, policy) in targets {
+            let key = format!("{}-{}-{}-{}", base.server_name, base.parent_address, base.target_address, base.check_type);
+            let prefix = location_key(&key, "");
+            let mut locations: Vec<CheckResult> = all.iter()
+                .filter(|(k, r)| k.starts_with(&prefix) && r.timestamp > cutoff)
+                .map(|(_, r)| r.clone())
+                .collect();
+            if locations.is_empty() {
+                continue;
+            }
+            locations.sort_by(|a, b| a.location.cmp(&b.location));
+            self.process_result(combine(base, policy, &locations)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_keys_keep_source_binding() {
+        let key = location_key("web-10.0.0.1-10.0.0.1-TCP:443@eth1", "node-a");
+        assert_eq!(base_key(&key), Some("web-10.0.0.1-10.0.0.1-TCP:443@eth1"));
+        assert_eq!(base_key("web-10.0.0.1-10.0.0.1-TCP:443@eth1"), None);
+
+        let prefix = location_key("web-10.0.0.1-10.0.0.1-TCP:443", "");
+        assert!(!key.starts_with(&prefix));
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+use regex::Regex;
+use crate::config::CheckType;
+use crate::engine::Monitor;
+use crate::engine::host::format_age;
+use crate::models::{LogCursor, ProbeOutcome, Status};
+
+/// Upper bound on bytes consumed per poll so a log flood cannot stall the worker.
+const MAX_READ: u64 = 8 * 1024 * 1024;
+
+#[cfg(unix)]
+fn file_identity(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Reads complete lines from `offset` onward and counts the ones matching `re`.
+fn scan(file: &mut File, offset: &mut u64, re: &Regex, last_match: &mut Option<String>) -> std::io::Result<u64> {
+    file.seek(SeekFrom::Start(*offset))?;
+    let mut buf = Vec::new();
+    file.take(MAX_READ).read_to_end(&mut buf)?;
+
+    // A trailing partial line is left for the next poll, unless it alone fills the read budget.
+    let consumed = match buf.iter().rposition(|b| *b == b'\n') {
+        Some(pos) => pos + 1,
+        None if buf.len() as u64 == MAX_READ => buf.len(),
+        None => 0,
+    };
+
+    let mut matches = 0;
+    for line in buf[..consumed].split(|b| *b == b'\n') {
+        let line = String::from_utf8_lossy(line);
+        if re.is_match(&line) {
+            matches += 1;
+            *last_match = Some(line.trim_end().chars().take(160).collect());
+        }
+    }
+    *offset += consumed as u64;
+    Ok(matches)
+}
+
+/// Advances the cursor over whatever was appended since the last poll, following rotation and truncation.
+fn poll(path: &str, re: &Regex, cursor: &mut LogCursor) -> Result<Option<u64>, String> {
+    let meta = std::fs::metadata(path);
+    let identity = meta.as_ref().ok().and_then(file_identity);
+    let rotated = cursor.file.is_some() && (meta.is_err() || identity != cursor.identity);
+
+    let mut matches = 0;
+    if rotated && let Some(mut old) = cursor.file.take() {
+        // The renamed file may have gained lines after our last read; finish it before moving on.
+        matches += scan(&mut old, &mut cursor.offset, re, &mut cursor.last_match).unwrap_or(0);
+        cursor.offset = 0;
+    }
+
+    let meta = match meta {
+        Ok(m) => m,
+        // Rotated away with no replacement yet; the next poll picks up the new file from its start.
+        Err(_) if rotated => return Ok(Some(matches)),
+        Err(e) => return Err(format!("Log {} unavailable: {}", path, e)),
+    };
+    let first_poll = !cursor.watching;
+    if cursor.file.is_none() {
+        let file = File::open(path).map_err(|e| format!("Log {} unreadable: {}", path, e))?;
+        cursor.file = Some(file);
+        cursor.identity = identity;
+        // Only lines written after the agent starts watching are counted.
+        cursor.offset = if first_poll { meta.len() } else { 0 };
+        cursor.watching = true;
+    } else if meta.len() < cursor.offset {
+        cursor.offset = 0; // truncated in place (copytruncate)
+    }
+
+    if first_poll {
+        return Ok(None);
+    }
+    if let Some(file) = cursor.file.as_mut() {
+        matches += scan(file, &mut cursor.offset, re, &mut cursor.last_match)
+            .map_err(|e| format!("Log {} read failed: {}", path, e))?;
+    }
+    Ok(Some(matches))
+}
+
+impl Monitor {
+    /// Tails a log from the saved cursor for `key` and grades the matches seen within the window.
+    pub async fn check_log(&self, key: &str, check: &CheckType) -> ProbeOutcome {
+        let CheckType::LogMatch { path, regex, window, threshold, .. } = check else {
+            return ProbeOutcome::new(Status::Unknown, "Not a log check");
+        };
+        let re = match Regex::new(regex) {
+            Ok(r) => r,
+            Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Invalid regex: {}", e)),
+        };
+
+        let mut cursor = self.state.lock().await.log_cursors.remove(key).unwrap_or_default();
+        let path_owned = path.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let polled = poll(&path_owned, &re, &mut cursor);
+            (cursor, polled)
+        });
+        let (mut cursor, polled) = match tokio::time::timeout(Duration::from_secs(5), task).await {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => return ProbeOutcome::new(Status::Unknown, format!("Host Probe Fault: {}", e)),
+            Err(_) => return ProbeOutcome::new(Status::Unknown, "Host Probe Timeout"),
+        };
+
+        let now = Instant::now();
+        let window_len = Duration::from_secs(*window);
+        let new_matches = match &polled {
+            Ok(Some(n)) => *n,
+            _ => 0,
+        };
+        if new_matches > 0 {
+            cursor.hits.push_back((now, new_matches));
+        }
+        while cursor.hits.front().is_some_and(|(t, _)| now.duration_since(*t) > window_len) {
+            cursor.hits.pop_front();
+        }
+        let in_window: u64 = cursor.hits.iter().map(|(_, n)| n).sum();
+        let last_match = cursor.last_match.clone();
+        self.state.lock().await.log_cursors.insert(key.to_string(), cursor);
+
+        let outcome = match polled {
+            Err(e) => ProbeOutcome::new(Status::Down, e),
+            Ok(None) => ProbeOutcome::new(Status::Up, format!("Tailing {} from end", path)),
+            Ok(Some(_)) if in_window >= *threshold => {
+                let excerpt = last_match.map_or(String::new(), |l| format!(": {}", l));
+                ProbeOutcome::new(Status::Down, format!("{} match(es) in last {} (threshold {}){}",
+                    in_window, format_age(*window as f64), threshold, excerpt))
+            }
+            Ok(Some(_)) => ProbeOutcome::new(Status::Up, format!("{} match(es) in last {} (threshold {})",
+                in_window, format_age(*window as f64), threshold)),
+        };
+        outcome.metric("matches", in_window as f64).metric("new_matches", new_matches as f64)
+    }
+}
@@ -0,0 +1,328 @@
+use std::time::{Duration, Instant};
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use crate::config::{CheckType, MailProtocol, MailTls, SourceBinding};
+use crate::engine::{net, tls};
+use crate::models::{ProbeOutcome, Status};
+
+enum Failure {
+    Connect(String),
+    Greeting(String),
+    Protocol(String),
+    Auth(String),
+}
+
+impl Failure {
+    fn into_outcome(self) -> ProbeOutcome {
+        match self {
+            Failure::Connect(e) => ProbeOutcome::new(Status::Down, format!("Connection Failed: {}", e)),
+            Failure::Greeting(line) => ProbeOutcome::new(Status::Down, format!("Port Open, Unexpected Greeting: {}", line)),
+            Failure::Protocol(e) => ProbeOutcome::new(Status::Down, format!("Protocol Error: {}", e)),
+            Failure::Auth(e) => ProbeOutcome::new(Status::Down, format!("Authentication Rejected: {}", e)),
+        }
+    }
+}
+
+enum Conn {
+    Plain(BufReader<TcpStream>),
+    Tls(Box<BufReader<TlsStream<TcpStream>>>),
+}
+
+impl Conn {
+    async fn read_line(&mut self) -> Result<String, Failure> {
+        let mut line = String::new();
+        let read = match self {
+            Conn::Plain(r) => r.read_line(&mut line).await,
+            Conn::Tls(r) => r.read_line(&mut line).await,
+        };
+        match read {
+            Ok(0) => Err(Failure::Protocol("Connection Closed By Peer".into())),
+            Ok(_) => Ok(line.trim_end().to_string()),
+            Err(e) => Err(Failure::Protocol(e.to_string())),
+        }
+    }
+
+    async fn send(&mut self, line: &str) -> Result<(), Failure> {
+        let data = format!("{}\r\n", line);
+        let write = match self {
+            Conn::Plain(r) => r.get_mut().write_all(data.as_bytes()).await,
+            Conn::Tls(r) => {
+                let s = r.get_mut();
+                match s.write_all(data.as_bytes()).await {
+                    Ok(()) => s.flush().await,
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        write.map_err(|e| Failure::Protocol(e.to_string()))
+    }
+
+    async fn upgrade(self, host: &str) -> Result<(Conn, Option<f64>), Failure> {
+        match self {
+            Conn::Plain(r) => {
+                let stream = tls::connect(r.into_inner(), host).await.map_err(Failure::Protocol)?;
+                let days = tls::cert_days_left(&stream);
+                Ok((Conn::Tls(Box::new(BufReader::new(stream))), days))
+            }
+            tls_conn => Ok((tls_conn, None)),
+        }
+    }
+}
+
+struct Session {
+    capabilities: Vec<String>,
+    cert_days_left: Option<f64>,
+    authenticated: bool,
+}
+
+/// Reads the mail greeting, lists capabilities and optionally upgrades and authenticates.
+pub async fn check_mail(address: &str, check: &CheckType, bind: &SourceBinding) -> ProbeOutcome {
+    let CheckType::Mail { protocol, port, tls: tls_mode, username, password, cert_warn_days, timeout_ms, .. } = check else {
+        return ProbeOutcome::new(Status::Unknown, "Not a mail check");
+    };
+    let port = port.unwrap_or_else(|| protocol.default_port(*tls_mode));
+    let credentials = username.as_deref().zip(password.as_deref());
+
+    // Plaintext AUTH would hand the password to anyone on the path.
+    if credentials.is_some() && *tls_mode == MailTls::None {
+        return ProbeOutcome::new(Status::Down, "Credentials require TLS");
+    }
+    let timeout = Duration::from_millis(*timeout_ms);
+
+    let start = Instant::now();
+    let stream = match tokio::time::timeout(timeout, net::connect_tcp(&net::host_port(address, port), bind)).await {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => return Failure::Connect(e.to_string()).into_outcome(),
+        Err(_) => return ProbeOutcome::new(Status::Down, "Connection Timeout"),
+    };
+    let connect_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let session = tokio::time::timeout(timeout, async {
+        let mut conn = Conn::Plain(BufReader::new(stream));
+        let mut cert_days_left = None;
+        if *tls_mode == MailTls::Implicit {
+            let (upgraded, days) = conn.upgrade(address).await?;
+            conn = upgraded;
+            cert_days_left = days;
+        }
+        match protocol {
+            MailProtocol::Smtp => smtp_session(conn, address, *tls_mode, credentials, cert_days_left).await,
+            MailProtocol::Imap => imap_session(conn, address, *tls_mode, credentials, cert_days_left).await,
+            MailProtocol::Pop3 => pop3_session(conn, address, *tls_mode, credentials, cert_days_left).await,
+        }
+    }).await;
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+    let session = match session {
+        Ok(Ok(s)) => s,
+        Ok(Err(f)) => return f.into_outcome().metric("connect_ms", connect_ms),
+        Err(_) => return ProbeOutcome::new(Status::Down, "Session Timeout").metric("connect_ms", connect_ms),
+    };
+
+    let caps = if session.capabilities.is_empty() {
+        "none advertised".to_string()
+    } else {
+        session.capabilities.join(", ")
+    };
+    let mut outcome = ProbeOutcome::new(Status::Up, format!("{} Ready{} [{}]",
+        protocol.label(), if session.authenticated { " (Authenticated)" } else { "" }, caps))
+        .metric("connect_ms", connect_ms)
+        .metric("session_ms", elapsed);
+    outcome.latency_ms = Some(elapsed);
+
+    if let Some(days) = session.cert_days_left {
+        outcome = outcome.metric("cert_days_left", days);
+        if days <= *cert_warn_days as f64 {
+            outcome.health = Status::Degraded;
+            outcome.message = format!("{} (Certificate expires in {:.0} days)", outcome.message, days);
+        }
+    }
+    outcome
+}
+
+async fn smtp_reply(conn: &mut Conn) -> Result<(u16, Vec<String>), Failure> {
+    let mut lines = Vec::new();
+    loop {
+        let line = conn.read_line().await?;
+        let code = line.get(..3).and_then(|c| c.parse::<u16>().ok())
+            .ok_or_else(|| Failure::Protocol(format!("Malformed reply: {}", line)))?;
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        lines.push(line.get(4..).unwrap_or("").to_string());
+        if last {
+            return Ok((code, lines));
+        }
+    }
+}
+
+async fn smtp_ehlo(conn: &mut Conn) -> Result<Vec<String>, Failure> {
+    conn.send("EHLO spectra.monitor").await?;
+    let (code, lines) = smtp_reply(conn).await?;
+    if code != 250 {
+        return Err(Failure::Protocol(format!("EHLO rejected: {} {}", code, lines.join(" "))));
+    }
+    Ok(lines.into_iter().skip(1).filter_map(|l| l.split_whitespace().next().map(str::to_uppercase)).collect())
+}
+
+async fn smtp_session(mut conn: Conn, host: &str, tls_mode: MailTls, credentials: Option<(&str, &str)>, mut cert_days_left: Option<f64>) -> Result<Session, Failure> {
+    let greeting = conn.read_line().await?;
+    if !greeting.starts_with("220") {
+        return Err(Failure::Greeting(greeting));
+    }
+    // Drain the rest of a multi-line greeting.
+    if greeting.as_bytes().get(3) == Some(&b'-') {
+        smtp_reply(&mut conn).await?;
+    }
+
+    let mut capabilities = smtp_ehlo(&mut conn).await?;
+    if tls_mode == MailTls::StartTls {
+        if !capabilities.iter().any(|c| c == "STARTTLS") {
+            return Err(Failure::Protocol("STARTTLS Not Advertised".into()));
+        }
+        conn.send("STARTTLS").await?;
+        let (code, lines) = smtp_reply(&mut conn).await?;
+        if code != 220 {
+            return Err(Failure::Protocol(format!("STARTTLS rejected: {} {}", code, lines.join(" "))));
+        }
+        let (upgraded, days) = conn.upgrade(host).await?;
+        conn = upgraded;
+        cert_days_left = days;
+        capabilities = smtp_ehlo(&mut conn).await?;
+    }
+
+    let mut authenticated = false;
+    if let Some((user, pass)) = credentials {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", user, pass));
+        conn.send(&format!("AUTH PLAIN {}", token)).await?;
+        let (code, lines) = smtp_reply(&mut conn).await?;
+        if code != 235 {
+            return Err(Failure::Auth(format!("{} {}", code, lines.join(" "))));
+        }
+        authenticated = true;
+    }
+
+    let _ = conn.send("QUIT").await;
+    Ok(Session { capabilities, cert_days_left, authenticated })
+}
+
+fn imap_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Sends a tagged IMAP command and collects untagged lines until its completion.
+async fn imap_command(conn: &mut Conn, tag: &str, command: &str) -> Result<(bool, String, Vec<String>), Failure> {
+    conn.send(&format!("{} {}", tag, command)).await?;
+    let mut untagged = Vec::new();
+    loop {
+        let line = conn.read_line().await?;
+        if let Some(rest) = line.strip_prefix(tag).and_then(|r| r.strip_prefix(' ')) {
+            return Ok((rest.starts_with("OK"), rest.to_string(), untagged));
+        }
+        untagged.push(line);
+    }
+}
+
+async fn imap_capabilities(conn: &mut Conn, tag: &str) -> Result<Vec<String>, Failure> {
+    let (ok, status, untagged) = imap_command(conn, tag, "CAPABILITY").await?;
+    if !ok {
+        return Err(Failure::Protocol(format!("CAPABILITY failed: {}", status)));
+    }
+    Ok(untagged.iter()
+        .filter_map(|l| l.strip_prefix("* CAPABILITY "))
+        .flat_map(|l| l.split_whitespace().map(str::to_uppercase))
+        .collect())
+}
+
+async fn imap_session(mut conn: Conn, host: &str, tls_mode: MailTls, credentials: Option<(&str, &str)>, mut cert_days_left: Option<f64>) -> Result<Session, Failure> {
+    let greeting = conn.read_line().await?;
+    if !(greeting.starts_with("* OK") || greeting.starts_with("* PREAUTH")) {
+        return Err(Failure::Greeting(greeting));
+    }
+
+    let mut capabilities = imap_capabilities(&mut conn, "s1").await?;
+    if tls_mode == MailTls::StartTls {
+        if !capabilities.iter().any(|c| c == "STARTTLS") {
+            return Err(Failure::Protocol("STARTTLS Not Advertised".into()));
+        }
+        let (ok, status, _) = imap_command(&mut conn, "s2", "STARTTLS").await?;
+        if !ok {
+            return Err(Failure::Protocol(format!("STARTTLS rejected: {}", status)));
+        }
+        let (upgraded, days) = conn.upgrade(host).await?;
+        conn = upgraded;
+        cert_days_left = days;
+        capabilities = imap_capabilities(&mut conn, "s3").await?;
+    }
+
+    let mut authenticated = false;
+    if let Some((user, pass)) = credentials {
+        let (ok, status, _) = imap_command(&mut conn, "s4", &format!("LOGIN {} {}", imap_quote(user), imap_quote(pass))).await?;
+        if !ok {
+            return Err(Failure::Auth(status));
+        }
+        authenticated = true;
+    }
+
+    let _ = conn.send("s5 LOGOUT").await;
+    Ok(Session { capabilities, cert_days_left, authenticated })
+}
+
+async fn pop3_command(conn: &mut Conn, command: &str) -> Result<(bool, String), Failure> {
+    conn.send(command).await?;
+    let line = conn.read_line().await?;
+    Ok((line.starts_with("+OK"), line))
+}
+
+async fn pop3_capabilities(conn: &mut Conn) -> Result<Vec<String>, Failure> {
+    let (ok, _) = pop3_command(conn, "CAPA").await?;
+    if !ok {
+        // CAPA is optional in POP3; servers without it simply advertise nothing.
+        return Ok(Vec::new());
+    }
+    let mut caps = Vec::new();
+    loop {
+        let line = conn.read_line().await?;
+        if line == "." {
+            return Ok(caps);
+        }
+        if let Some(cap) = line.split_whitespace().next() {
+            caps.push(cap.to_uppercase());
+        }
+    }
+}
+
+async fn pop3_session(mut conn: Conn, host: &str, tls_mode: MailTls, credentials: Option<(&str, &str)>, mut cert_days_left: Option<f64>) -> Result<Session, Failure> {
+    let greeting = conn.read_line().await?;
+    if !greeting.starts_with("+OK") {
+        return Err(Failure::Greeting(greeting));
+    }
+
+    let mut capabilities = pop3_capabilities(&mut conn).await?;
+    if tls_mode == MailTls::StartTls {
+        let (ok, line) = pop3_command(&mut conn, "STLS").await?;
+        if !ok {
+            return Err(Failure::Protocol(format!("STLS rejected: {}", line)));
+        }
+        let (upgraded, days) = conn.upgrade(host).await?;
+        conn = upgraded;
+        cert_days_left = days;
+        capabilities = pop3_capabilities(&mut conn).await?;
+    }
+
+    let mut authenticated = false;
+    if let Some((user, pass)) = credentials {
+        let (ok, line) = pop3_command(&mut conn, &format!("USER {}", user)).await?;
+        if !ok {
+            return Err(Failure::Auth(line));
+        }
+        let (ok, line) = pop3_command(&mut conn, &format!("PASS {}", pass)).await?;
+        if !ok {
+            return Err(Failure::Auth(line));
+        }
+        authenticated = true;
+    }
+
+    let _ = conn.send("QUIT").await;
+    Ok(Session { capabilities, cert_days_left, authenticated })
+}
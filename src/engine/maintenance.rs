@@ -0,0 +1,86 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::warn;
+use crate::config::{MonitorConfig, Server};
+use crate::engine::Monitor;
+use crate::models::{CheckResult, Silence};
+
+/// Names the first configured window or silence that covers `result` at `now`.
+pub fn covering(cfg: &MonitorConfig, silences: &[Silence], server: Option<&Server>, result: &CheckResult, now: DateTime<Utc>) -> Option<String> {
+    let labels = server.map_or(&[][..], |s| s.labels.as_slice());
+    let matches = |scope: &crate::config::MaintenanceScope| {
+        scope.matches(&result.category, &result.server_name, &result.check_type, labels)
+    };
+    cfg.maintenance.iter()
+        .find(|w| matches(&w.scope) && w.is_active(now))
+        .map(|w| format!("window {}", w.name))
+        .or_else(|| silences.iter()
+            .find(|s| s.expires_at > now && matches(&s.scope))
+            .map(Silence::describe))
+}
+
+impl Monitor {
+    /// Reloads silences from Redis so ones created through any node's API apply everywhere.
+    pub async fn refresh_silences(&self) {
+        let Some(redis) = &self.redis else {
+            let now = Utc::now();
+            self.state.lock().await.silences.retain(|s| s.expires_at > now);
+            return;
+        };
+        match redis.fetch_silences().await {
+            Ok(silences) => self.state.lock().await.silences = silences,
+            Err(e) => warn!("[MAINTENANCE] Failed to load silences: {}", e),
+        }
+    }
+
+    pub async fn active_silences(&self) -> Vec<Silence> {
+        let now = Utc::now();
+        let mut silences: Vec<Silence> = self.state.lock().await.silences.iter()
+            .filter(|s| s.expires_at > now)
+            .cloned()
+            .collect();
+        silences.sort_by_key(|s| s.expires_at);
+        silences
+    }
+
+    pub async fn add_silence(&self, silence: Silence) -> Result<()> {
+        if let Some(redis) = &self.redis {
+            redis.push_silence(&silence).await?;
+        }
+        self.state.lock().await.silences.push(silence);
+        Ok(())
+    }
+
+    pub async fn remove_silence(&self, id: &str) -> Result<bool> {
+        let mut removed = false;
+        if let Some(redis) = &self.redis {
+            removed = redis.delete_silence(id).await?;
+        }
+        let mut state = self.state.lock().await;
+        let before = state.silences.len();
+        state.silences.retain(|s| s.id != id);
+        Ok(removed || state.silences.len() < before)
+    }
+
+    /// Counts `result` towards its check's uptime unless it fell in maintenance, and returns the percentage.
+    pub async fn record_uptime(&self, key: &str, result: &CheckResult) -> Option<f64> {
+        let up = result.maintenance.is_none().then_some(result.status);
+        let shared = match &self.redis {
+            Some(redis) => redis.record_uptime(key, up).await.ok(),
+            None => None,
+        };
+        let (ups, total) = match shared {
+            Some(counts) => counts,
+            None => {
+                let mut state = self.state.lock().await;
+                let counts = state.uptime.entry(key.to_string()).or_default();
+                if let Some(up) = up {
+                    counts.0 += up as u64;
+                    counts.1 += 1;
+                }
+                *counts
+            }
+        };
+        (total > 0).then(|| ups as f64 * 100.0 / total as f64)
+    }
+}
@@ -5,26 +5,52 @@ use hickory_resolver::config::ResolverConfig;
 use hickory_resolver::name_server::TokioConnectionProvider;
 use hickory_resolver::TokioResolver;
 use ipnet::IpNet;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use surge_ping::{Client as PingClient, Config as PingConfig};
 use tokio::sync::{Mutex, Semaphore, RwLock};
 use tracing::{error, info, warn};
 
-use crate::config::{MonitorConfig, CheckType};
-use crate::models::{CheckResult, MonitorState, Status};
+use crate::config::{CheckMode, MonitorConfig};
+use crate::models::{CheckResult, MonitorState, NodeInfo, Status};
+use hysteresis::Transition;
 use crate::redis_manager::RedisManager;
 
+pub mod assign;
 pub mod checks;
+pub mod command;
+pub mod content;
+pub mod database;
+pub mod flow;
+pub mod grpc;
+pub mod host;
+pub mod hysteresis;
+pub mod locations;
+pub mod logs;
+pub mod mail;
+pub mod maintenance;
+pub mod mqtt;
+pub mod net;
 pub mod notifications;
+pub mod ntp;
+pub mod quorum;
+pub mod ratelimit;
+pub mod schedule;
+pub mod snmp;
+pub mod tls;
+pub mod trace;
+pub mod websocket;
 
 pub struct Monitor {
     pub config: Arc<RwLock<MonitorConfig>>,
     pub(crate) ping_client: PingClient,
     pub state: Arc<Mutex<MonitorState>>,
     pub(crate) http_client: reqwest::Client,
+    pub(crate) bound_clients: net::BoundClients,
     pub(crate) concurrency_limiter: Arc<Semaphore>,
+    pub(crate) rate_limiter: ratelimit::RateLimiter,
     pub(crate) dns_resolver: TokioResolver,
     pub redis: Option<RedisManager>,
 }
@@ -62,11 +88,25 @@ impl Monitor {
             state: Arc::new(Mutex::new(MonitorState {
                 last_results: HashMap::new(),
                 node_id,
+                node_info: NodeInfo::default(),
                 live_nodes: Vec::new(),
+                live_node_info: HashMap::new(),
                 last_loss_alerts: HashMap::new(),
+                counter_samples: HashMap::new(),
+                snmp_keys: HashMap::new(),
+                last_mtr_runs: HashMap::new(),
+                last_runs: HashMap::new(),
+                content_snapshots: HashMap::new(),
+                log_cursors: HashMap::new(),
+                suppressed_transitions: HashMap::new(),
+                silences: Vec::new(),
+                uptime: HashMap::new(),
+                transitions: HashMap::new(),
             })),
             http_client: reqwest::Client::new(),
+            bound_clients: net::BoundClients::default(),
             concurrency_limiter: Arc::new(Semaphore::new(max_concurrent)),
+            rate_limiter: ratelimit::RateLimiter::default(),
             dns_resolver,
             redis,
         })
@@ -90,12 +130,7 @@ impl Monitor {
 
                 for address in addresses {
                     for check in server.checks.iter() {
-                        let check_type_name = match check {
-                            CheckType::Ping { .. } => "ICMP".into(),
-                            CheckType::TcpPort { port, .. } => format!("TCP:{}", port),
-                            CheckType::UdpPort { port, .. } => format!("UDP:{}", port),
-                            CheckType::Http { method, .. } => format!("HTTP:{}", method.as_deref().unwrap_or("GET")),
-                        };
+                        let check_type_name = check.name();
                         let key = format!("{}-{}-{}-{}", server.name, server.address, address, check_type_name);
                         active_keys.insert(key);
                     }
@@ -108,14 +143,17 @@ impl Monitor {
                 Ok(cached) => {
                     let mut state = self.state.lock().await;
                     
+                    // Per-location results are kept for as long as the check they belong to.
+                    let is_active = |key: &String| active_keys.contains(key)
+                        || locations::base_key(key).is_some_and(|base| active_keys.contains(base));
                     for key in cached.keys() {
-                        if !active_keys.contains(key) {
+                        if !is_active(key) {
                             let _ = redis.delete_result(key).await;
                         }
                     }
 
                     state.last_results = cached.into_iter()
-                        .filter(|(k, _)| active_keys.contains(k))
+                        .filter(|(k, _)| is_active(k))
                         .collect();
 
                     info!("Restored {} valid tracking points from Redis cache", state.last_results.len());
@@ -136,29 +174,18 @@ impl Monitor {
 
                 for address in addresses {
                     for (chk_idx, check) in server.checks.iter().enumerate() {
-                        let check_type_name = match check {
-                            CheckType::Ping { .. } => "ICMP".into(),
-                            CheckType::TcpPort { port, .. } => format!("TCP:{}", port),
-                            CheckType::UdpPort { port, .. } => format!("UDP:{}", port),
-                            CheckType::Http { method, .. } => format!("HTTP:{}", method.as_deref().unwrap_or("GET")),
-                        };
+                        let check_type_name = check.name();
                         let key = format!("{}-{}-{}-{}", server.name, server.address, address, check_type_name);
                         
-                        state.last_results.entry(key).or_insert(CheckResult {
-                            category: category.name.clone(),
-                            server_name: server.name.clone(),
-                            parent_address: server.address.clone(),
-                            target_address: address.clone(),
-                            timestamp: now,
-                            check_type: check_type_name,
-                            status: false,
-                            latency_ms: None,
-                            packet_loss: None,
-                            message: "Awaiting Infrastructure Handshake...".into(),
-                            category_order: cat_idx,
-                            server_order: srv_idx,
-                            check_order: chk_idx,
-                            provider_node: None,
+                        state.last_results.entry(key).or_insert_with(|| {
+                            let mut pending = CheckResult::new(&server.name, &server.address, &address, check_type_name);
+                            pending.category = category.name.clone();
+                            pending.timestamp = now;
+                            pending.message = "Awaiting Infrastructure Handshake...".into();
+                            pending.category_order = cat_idx;
+                            pending.server_order = srv_idx;
+                            pending.check_order = chk_idx;
+                            pending
                         });
                     }
                 }
@@ -176,26 +203,32 @@ impl Monitor {
         
         self.initialize_state().await;
         
-        let node_id = {
+        let (node_id, node_info) = {
             let state = self.state.lock().await;
-            state.node_id.clone()
+            (state.node_id.clone(), state.node_info.clone())
         };
+        if !node_info.labels.is_empty() {
+            info!("--- Node Labels: {} ---", assign::describe(&node_info.labels));
+        }
         
         if let Some(redis) = &self.redis {
             let redis_clone = redis.clone();
             let state_clone = Arc::clone(&self.state);
             let nid = node_id.clone();
+            let info = node_info.clone();
             
             tokio::spawn(async move {
                 let mut is_online = true;
                 let mut last_nodes: Vec<String> = Vec::new();
 
                 loop {
-                    let _ = redis_clone.register_node(&nid).await;
+                    let _ = redis_clone.register_node(&nid, &info).await;
                     match redis_clone.cleanup_dead_nodes().await {
-                        Ok(current_nodes) => {
+                        Ok(live) => {
+                            let current_nodes: Vec<String> = live.iter().map(|(id, _)| id.clone()).collect();
                             let mut state = state_clone.lock().await;
                             state.live_nodes = current_nodes.clone();
+                            state.live_node_info = live.into_iter().collect();
                             
                             if !is_online {
                                 warn!("--- Redis Cluster Mesh Link Restored ---");
@@ -235,11 +268,14 @@ impl Monitor {
                     tokio::time::sleep(Duration::from_secs(1)).await;
                 }
             });
+
+            tokio::spawn(Arc::clone(&self).serve_confirmations(node_id.clone()));
         }
 
         let mut first_run = true;
         loop {
-            let interval = self.config.read().await.check_interval;
+            // Checks on a faster failure interval need the loop to come round that often.
+            let interval = schedule::tick(&*self.config.read().await);
             let now_ms = Utc::now().timestamp_millis() as u64;
             let interval_ms = interval * 1000;
             let next_tick_ms = ((now_ms / interval_ms) + 1) * interval_ms;
@@ -252,20 +288,27 @@ impl Monitor {
 
             let start_time = Utc::now();
             let mut tasks = FuturesUnordered::new();
+            self.refresh_silences().await;
 
-            let live_nodes = if self.redis.is_some() {
+            let mut nodes: Vec<(String, NodeInfo)> = if self.redis.is_some() {
                 let state = self.state.lock().await;
-                if state.live_nodes.is_empty() {
-                    vec![node_id.clone()]
-                } else {
-                    state.live_nodes.clone()
-                }
+                state.live_nodes.iter()
+                    .map(|id| (id.clone(), state.live_node_info.get(id).cloned().unwrap_or_default()))
+                    .collect()
             } else {
-                vec![node_id.clone()]
+                Vec::new()
             };
+            // Before the first heartbeat the peers are unknown, so a missing eligible node proves nothing.
+            let mesh_known = self.redis.is_none() || !nodes.is_empty();
+            // Until the first heartbeat lands this node is missing from the list, but it still works.
+            if !nodes.iter().any(|(id, _)| id == &node_id) {
+                nodes.push((node_id.clone(), node_info.clone()));
+                nodes.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            let all_weighted = assign::eligible(&nodes, &Default::default());
 
-            let node_index = live_nodes.iter().position(|id| id == &node_id).unwrap_or(0);
-            let total_nodes = live_nodes.len();
+            let node_index = nodes.iter().position(|(id, _)| id == &node_id).unwrap_or(0);
+            let total_nodes = nodes.len();
 
             if total_nodes > 1 {
                 info!("Mesh Sync: Handshaking with cluster... [Local Node {}/{}]", node_index + 1, total_nodes);
@@ -273,8 +316,11 @@ impl Monitor {
 
             let mut skipped = 0;
             let mut performed = 0;
-            let mut global_idx = 0;
+            let mut combined = Vec::new();
+            let mut unplaced = Vec::new();
+            let deferred = Arc::new(AtomicUsize::new(0));
 
+            let mut live_samples = HashSet::new();
             let cfg = self.config.read().await;
             for (cat_idx, category) in cfg.categories.iter().enumerate() {
                 for (srv_idx, server) in category.servers.iter().enumerate() {
@@ -287,24 +333,75 @@ impl Monitor {
 
                     for address in addresses {
                         for (chk_idx, check) in server.checks.iter().enumerate() {
-                            let check_type_name = match check {
-                                CheckType::Ping { .. } => "ICMP".into(),
-                                CheckType::TcpPort { port, .. } => format!("TCP:{}", port),
-                                CheckType::UdpPort { port, .. } => format!("UDP:{}", port),
-                                CheckType::Http { method, .. } => format!("HTTP:{}", method.as_deref().unwrap_or("GET")),
-                            };
+                            let check_type_name = check.name();
                             
                             let key = format!("{}-{}-{}-{}", server.name, server.address, address, check_type_name);
-                            
-                            let item_idx = global_idx;
-                            global_idx += 1;
-                            
-                            if (item_idx % total_nodes) != node_index {
-                                skipped += 1;
-                                continue;
+                            live_samples.extend(snmp::sample_keys(&key, check));
+                            let all_nodes = self.redis.is_some() && !check.is_local()
+                                && server.check_mode(category) == CheckMode::AllNodes;
+                            let run_key = if all_nodes { locations::location_key(&key, &node_id) } else { key.clone() };
+
+                            let check_interval = {
+                                let state = self.state.lock().await;
+                                let check_interval = schedule::interval_for(&cfg, server, state.last_results.get(&run_key));
+                                // Half a tick of slack absorbs the drift between cycle starts.
+                                let due_after = chrono::Duration::milliseconds((check_interval * 1000 - interval * 500) as i64);
+                                if state.last_runs.get(&run_key).is_some_and(|last| start_time - *last < due_after) {
+                                    continue;
+                                }
+                                check_interval
+                            };
+
+                            if check.is_local() {
+                                // Host checks never leave the node they are pinned to.
+                                if server.node.as_deref().is_some_and(|n| n != node_id) {
+                                    continue;
+                                }
+                            } else {
+                                let selector = server.selector(check);
+                                let eligible = assign::eligible(&nodes, &selector);
+                                if eligible.is_empty() {
+                                    // Nobody may run it; whichever node would own it unrestricted says so.
+                                    if mesh_known && assign::owner(&key, &all_weighted) == Some(node_id.as_str()) {
+                                        let mut res = CheckResult::new(&server.name, &server.address, &address, check_type_name.clone());
+                                        res.set_health(Status::Unknown);
+                                        res.message = format!("No Eligible Node Alive (run_on: {})", assign::describe(&selector));
+                                        res.category = category.name.clone();
+                                        res.category_order = cat_idx;
+                                        res.server_order = srv_idx;
+                                        res.check_order = chk_idx;
+                                        res.provider_node = Some(node_id.clone());
+                                        unplaced.push(res);
+                                        self.state.lock().await.last_runs.insert(run_key.clone(), start_time);
+                                    }
+                                    skipped += 1;
+                                    continue;
+                                }
+                                let owner = assign::owner(&key, &eligible) == Some(node_id.as_str());
+
+                                if all_nodes {
+                                    // Every eligible node checks; the shard owner combines the locations and alerts.
+                                    if !eligible.iter().any(|(id, _)| id == &node_id) {
+                                        skipped += 1;
+                                        continue;
+                                    }
+                                    if owner {
+                                        let mut base = CheckResult::new(&server.name, &server.address, &address, check_type_name.clone());
+                                        base.category = category.name.clone();
+                                        base.category_order = cat_idx;
+                                        base.server_order = srv_idx;
+                                        base.check_order = chk_idx;
+                                        base.provider_node = Some(node_id.clone());
+                                        combined.push((base, server.status_policy.unwrap_or(cfg.status_policy)));
+                                    }
+                                } else if !owner {
+                                    skipped += 1;
+                                    continue;
+                                }
                             }
 
                             performed += 1;
+                            self.state.lock().await.last_runs.insert(run_key, start_time);
 
                             let monitor_ref = Arc::clone(&self);
                             let s_clone = server.clone();
@@ -313,27 +410,50 @@ impl Monitor {
                             let cat_name = category.name.clone();
                             let nid_clone = node_id.clone();
                             let key_clone = key.clone();
-                            let interval = cfg.check_interval;
+                            let interval = check_interval;
                             let max_rps = cfg.max_checks_per_second;
+                            let per_host = if check.is_local() { None } else { server.max_checks_per_second_per_host.or(cfg.max_checks_per_second_per_host) };
+                            let deferred_ref = Arc::clone(&deferred);
+                            let confirm_from = if check.is_local() || all_nodes { 0 } else { server.confirm_from.unwrap_or(cfg.confirm_from) };
+                            let min_replies = server.confirm_min_replies.unwrap_or(cfg.confirm_min_replies);
                             
                             tasks.push(tokio::spawn(async move {
+                                // Over the limit a check waits its turn rather than being dropped and going stale.
+                                let host = ratelimit::target_host(&a_clone);
+                                let mut held = monitor_ref.rate_limiter.acquire(&host, max_rps as f64, per_host).await;
                                 if let Some(redis) = &monitor_ref.redis {
-                                    if redis.is_rate_limited(max_rps).await.unwrap_or(false) {
-                                        return None;
+                                    // The cluster-wide count is per wall-clock second, so retry in the next one.
+                                    while max_rps > 0 && redis.is_rate_limited(max_rps).await.unwrap_or(false) {
+                                        let into_second = Utc::now().timestamp_subsec_millis() as u64;
+                                        let pause = Duration::from_millis(1000 - into_second.min(999) + rand::random::<u64>() % 100);
+                                        tokio::time::sleep(pause).await;
+                                        held += pause;
                                     }
+                                }
+                                if !held.is_zero() {
+                                    deferred_ref.fetch_add(1, Ordering::Relaxed);
+                                }
 
+                                if let Some(redis) = &monitor_ref.redis {
                                     let ttl = (interval * 1000) * 8 / 10;
-                                    if !redis.try_acquire_lock(&key_clone, &nid_clone, ttl).await {
+                                    if !all_nodes && !redis.try_acquire_lock(&key_clone, &nid_clone, ttl).await {
                                         return None;
                                     }
                                 }
 
-                                let _permit = monitor_ref.concurrency_limiter.acquire().await.ok();
-                                let mut res = monitor_ref.run_check_with_retry(s_clone, a_clone, c_clone).await;
+                                let permit = monitor_ref.concurrency_limiter.acquire().await.ok();
+                                let mut res = monitor_ref.run_check_with_retry(s_clone.clone(), a_clone.clone(), c_clone.clone()).await;
+                                drop(permit);
                                 res.category = cat_name;
+                                if !res.status && confirm_from > 0 {
+                                    monitor_ref.confirm_failure(&mut res, &s_clone, &a_clone, &c_clone, confirm_from, min_replies).await;
+                                }
                                 res.category_order = cat_idx;
                                 res.server_order = srv_idx;
                                 res.check_order = chk_idx;
+                                if all_nodes {
+                                    res.location = Some(nid_clone.clone());
+                                }
                                 res.provider_node = Some(nid_clone);
                                 Some(res)
                             }));
@@ -344,7 +464,10 @@ impl Monitor {
                 }
             }
 
-            let mut results = Vec::with_capacity(tasks.len());
+            // Samples of removed checks or OIDs would otherwise live for as long as the agent does.
+            self.state.lock().await.counter_samples.retain(|k, _| live_samples.contains(k));
+
+            let mut results = unplaced;
             let total = tasks.len();
             while let Some(join_res) = tasks.next().await {
                 if let Ok(Some(result)) = join_res {
@@ -352,18 +475,51 @@ impl Monitor {
                 }
             }
 
+            // Parents are processed first so their children see this cycle's outage.
+            let depths = cfg.dependency_depths();
+            let depth = |r: &CheckResult| depths.get(&r.server_name).copied().unwrap_or(0);
             results.sort_by(|a, b| {
-                a.category_order.cmp(&b.category_order)
+                depth(a).cmp(&depth(b))
+                    .then(a.category_order.cmp(&b.category_order))
                     .then(a.server_order.cmp(&b.server_order))
                     .then(a.server_name.cmp(&b.server_name))
                     .then(a.check_type.cmp(&b.check_type))
             });
 
+            if let Some(redis) = &self.redis {
+                self.sync_parent_results(redis, &cfg).await;
+            }
+
             for result in results {
-                self.process_result(result).await;
+                if result.location.is_some() {
+                    self.record_location(result).await;
+                } else {
+                    self.process_result(result).await;
+                }
+            }
+            let max_age = chrono::Duration::seconds((cfg.check_interval * 3) as i64);
+            self.combine_locations(combined, max_age).await;
+
+            let suppressed = std::mem::take(&mut self.state.lock().await.suppressed_transitions);
+            for (parent, children) in suppressed {
+                // A parent under planned maintenance takes its dependents with it without paging anyone.
+                let planned = self.state.lock().await.last_results.values()
+                    .any(|r| r.server_name == parent && r.maintenance.is_some());
+                if planned {
+                    continue;
+                }
+                warn!("[DEPENDENCY] {} down: suppressed {} dependent transition(s)", parent, children.len());
+                if cfg.webhook_url.is_some() || cfg.ntfy_topic.is_some() {
+                    let this = Arc::clone(&self);
+                    tokio::spawn(async move { this.dispatch_dependency_summary(parent, children).await; });
+                }
             }
 
             let duration = Utc::now() - start_time;
+            let deferred = deferred.load(Ordering::Relaxed);
+            if deferred > 0 {
+                info!("[RATE] Deferred {} check(s) to stay under the rate limit", deferred);
+            }
             if total > 0 {
                 info!("Turbo cycle: {} performed, {} delegated to cluster. Finished in {:.2}s.", 
                     performed,
@@ -374,12 +530,64 @@ impl Monitor {
         }
     }
 
-    pub async fn process_result(self: &Arc<Self>, result: CheckResult) {
+    pub async fn process_result(self: &Arc<Self>, mut result: CheckResult) {
         let key = format!("{}-{}-{}-{}", result.server_name, result.parent_address, result.target_address, result.check_type);
-        let new_status = if result.status { Status::Up } else { Status::Down };
         
         // Fetch config once at the start
         let cfg = self.config.read().await;
+
+        let server_cfg = cfg.categories.iter()
+            .filter(|c| c.name == result.category)
+            .flat_map(|c| c.servers.iter())
+            .find(|s| s.name == result.server_name);
+        let traceable = server_cfg
+            .and_then(|s| s.checks.iter().find(|c| c.name() == result.check_type))
+            .is_some_and(|c| c.is_traceable());
+        let mtr_enabled = traceable && server_cfg.is_some_and(|s| s.mtr);
+
+        if !result.status && let Some(server) = server_cfg.filter(|s| !s.depends_on.is_empty()) {
+            let state = self.state.lock().await;
+            if let Some(parent) = server.depends_on.iter().find(|p| state.server_down(p)) {
+                result.set_health(Status::Unknown);
+                result.message = format!("Unreachable (parent down: {}) - {}", parent, result.message);
+                // Grandchildren are attributed to the outage at the top of the chain.
+                let root = state.last_results.values()
+                    .filter(|r| &r.server_name == parent)
+                    .find_map(|r| r.parent_down.clone());
+                result.parent_down = Some(root.unwrap_or_else(|| parent.clone()));
+            }
+        }
+        let new_status = result.health;
+
+        let silences = self.state.lock().await.silences.clone();
+        result.maintenance = maintenance::covering(&cfg, &silences, server_cfg, &result, Utc::now());
+        result.uptime_pct = self.record_uptime(&key, &result).await;
+        let silenced = result.maintenance.is_some();
+
+        let policy = hysteresis::Policy {
+            fail_after: server_cfg.and_then(|s| s.fail_after).unwrap_or(cfg.fail_after),
+            recover_after: server_cfg.and_then(|s| s.recover_after).unwrap_or(cfg.recover_after),
+            flap: cfg.flap_detection.as_ref(),
+        };
+        let (transition, announced, confirmed) = {
+            let mut state = self.state.lock().await;
+            let tracker = state.transitions.entry(key.clone()).or_default();
+            let transition = hysteresis::observe(tracker, new_status, &policy);
+            if tracker.flapping {
+                result.health = Status::Flapping;
+                if let Some(flap) = policy.flap {
+                    result.metrics.insert("flap_pct".into(), hysteresis::flap_pct(tracker, flap));
+                }
+            }
+            (transition, tracker.announced, tracker.confirmed)
+        };
+
+        // Paths stay attached while they are still relevant: the target is down or MTR keeps them fresh.
+        if result.trace.is_empty() && traceable && (mtr_enabled || !result.status)
+            && let Some(previous) = self.state.lock().await.last_results.get(&key)
+        {
+            result.trace = previous.trace.clone();
+        }
         
         if let Some(redis) = &self.redis {
             let _ = redis.push_result(&key, &result).await;
@@ -409,7 +617,7 @@ impl Monitor {
             t
         };
 
-        if let Some(loss) = result.packet_loss {
+        if let Some(loss) = result.packet_loss.filter(|_| !silenced) {
             let last_alert_loss = state_lock.last_loss_alerts.get(&key).cloned().unwrap_or(0.0);
             
             if loss >= threshold && last_alert_loss < threshold && result.status {
@@ -426,32 +634,104 @@ impl Monitor {
                 tokio::spawn(async move { this.dispatch_loss_notification(res_clone, loss, threshold, false).await; });
             }
         }
+
+        if let Some(change) = result.content_change.clone().filter(|_| !silenced) {
+            warn!("[CONTENT] {}/{} ({}) changed {} -> {}", result.server_name, result.check_type, result.target_address, &change.previous_hash[..12], &change.hash[..12]);
+            let this = Arc::clone(self);
+            let res_clone = result.clone();
+            tokio::spawn(async move { this.dispatch_content_notification(res_clone, change).await; });
+        }
+
+        let mtr_due = mtr_enabled && state_lock.last_mtr_runs.get(&key)
+            .is_none_or(|t| t.elapsed() >= Duration::from_secs(cfg.traceroute.mtr_interval));
+        if mtr_due {
+            state_lock.last_mtr_runs.insert(key.clone(), std::time::Instant::now());
+            let this = Arc::clone(self);
+            let (mtr_key, address, probes) = (key.clone(), result.target_address.clone(), cfg.traceroute.mtr_probes);
+            tokio::spawn(async move {
+                match this.trace_target(&address, probes).await {
+                    Ok(hops) => this.store_trace(&mtr_key, hops).await,
+                    Err(e) => warn!("[MTR] {}: {}", mtr_key, e),
+                }
+            });
+        }
         drop(state_lock);
-        
-        let is_awaiting = old_result.as_ref().map_or(true, |r| r.message == "Awaiting Infrastructure Handshake...");
-        let old_status = old_result.map(|r| if r.status { Status::Up } else { Status::Down });
 
-        let old = match old_status {
-            Some(old) if old != new_status => Some(old),
-            _ if is_awaiting && new_status == Status::Down => Some(Status::Up),
-            _ => None,
+        // Leaving maintenance alerts only if the check is still unhealthy, as if it had been Up all along.
+        let left_maintenance = !silenced && old_result.as_ref().is_some_and(|r| r.maintenance.is_some());
+        let (old, new) = match transition {
+            Transition::Confirmed { from: None, to: Status::Up } => return,
+            Transition::Confirmed { from, to } => (from.unwrap_or(Status::Up), to),
+            Transition::FlapStarted { from } => (from.unwrap_or(Status::Up), Status::Flapping),
+            Transition::FlapEnded { to } => (Status::Flapping, to),
+            Transition::None => match confirmed {
+                Some(state) if left_maintenance && !announced && state != Status::Up && result.health != Status::Flapping => (Status::Up, state),
+                _ => return,
+            },
         };
 
-        let old = match old {
-            Some(o) => o,
-            None => return,
-        };
+        let mut msg = format!("[CHANGE] {}/{} ({}) -> {:?}", result.server_name, result.check_type, result.target_address, new);
+        if let Some(maintenance) = &result.maintenance {
+            msg.push_str(&format!(" [{}]", maintenance));
+        }
+        if new == Status::Down { error!("{}", msg); } else { warn!("{}", msg); }
 
-        let msg = format!("[CHANGE] {}/{} ({}) -> {:?}", result.server_name, result.check_type, result.target_address, new_status);
-        if new_status == Status::Down { error!("{}", msg); } else { warn!("{}", msg); }
+        // Recoveries are only worth sending for failures someone was told about.
+        let deliver = !silenced && result.parent_down.is_none() && (new != Status::Up || announced);
+        if let Some(tracker) = self.state.lock().await.transitions.get_mut(&key) {
+            tracker.announced = deliver;
+        }
 
-        if is_awaiting && new_status == Status::Up {
+        // Children behind a down parent are summarized once per cycle.
+        if let Some(parent) = result.parent_down.as_ref().filter(|_| !silenced) {
+            self.state.lock().await.suppressed_transitions.entry(parent.clone()).or_default()
+                .push(format!("{}/{}", result.server_name, result.check_type));
+            return;
+        }
+        if !deliver {
             return;
         }
 
-        if cfg.webhook_url.is_some() || cfg.ntfy_topic.is_some() {
+        let trace_on_down = new == Status::Down && traceable && cfg.traceroute.on_down;
+        let notify = cfg.webhook_url.is_some() || cfg.ntfy_topic.is_some();
+        if trace_on_down || notify {
             let this = Arc::clone(self);
-            tokio::spawn(async move { this.dispatch_notifications(result, old, new_status).await; });
+            let probes = cfg.traceroute.probes;
+            tokio::spawn(async move {
+                if trace_on_down {
+                    match this.trace_target(&result.target_address, probes).await {
+                        Ok(hops) => {
+                            result.trace = hops.clone();
+                            this.store_trace(&key, hops).await;
+                        }
+                        Err(e) => warn!("[TRACE] {}/{}: {}", result.server_name, result.check_type, e),
+                    }
+                }
+                if notify {
+                    this.dispatch_notifications(result, old, new).await;
+                }
+            });
+        }
+    }
+
+    /// Pulls other nodes' latest results for dependency parents so outages they observed count here too.
+    async fn sync_parent_results(&self, redis: &RedisManager, cfg: &MonitorConfig) {
+        let parents: std::collections::HashSet<&str> = cfg.servers()
+            .flat_map(|s| s.depends_on.iter().map(String::as_str))
+            .collect();
+        if parents.is_empty() {
+            return;
+        }
+        let Ok(remote) = redis.fetch_all_results().await else { return };
+        let mut state = self.state.lock().await;
+        for (key, result) in remote {
+            if !parents.contains(result.server_name.as_str()) {
+                continue;
+            }
+            let newer = state.last_results.get(&key).is_none_or(|local| result.timestamp > local.timestamp);
+            if newer {
+                state.last_results.insert(key, result);
+            }
         }
     }
 
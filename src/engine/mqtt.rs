@@ -0,0 +1,297 @@
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::config::{default_mqtt_port, CheckType, SourceBinding};
+use crate::engine::{net, tls};
+use crate::models::{ProbeOutcome, Status};
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const DISCONNECT: u8 = 0xE0;
+const KEEP_ALIVE_SECS: u16 = 30;
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+fn connack_reason(code: u8) -> &'static str {
+    match code {
+        0 => "Connection Accepted",
+        1 => "Unacceptable Protocol Version",
+        2 => "Client Identifier Rejected",
+        3 => "Server Unavailable",
+        4 => "Bad Username or Password",
+        5 => "Not Authorized",
+        _ => "Unknown Return Code",
+    }
+}
+
+/// MQTT strings carry a two-byte length prefix.
+fn str_len(field: &str, s: &str) -> Result<u16, String> {
+    u16::try_from(s.len()).map_err(|_| format!("MQTT {} exceeds 65535 bytes", field))
+}
+
+fn put_str(buf: &mut Vec<u8>, field: &str, s: &str) -> Result<(), String> {
+    buf.extend_from_slice(&str_len(field, s)?.to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+/// Prefixes `body` with the fixed header and its variable-length remaining length.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(body);
+    out
+}
+
+fn connect_packet(client_id: &str, username: Option<&str>, password: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut flags = 0x02; // clean session
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    put_str(&mut body, "protocol name", "MQTT")?;
+    body.push(4); // protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    put_str(&mut body, "client ID", client_id)?;
+    if let Some(user) = username {
+        put_str(&mut body, "username", user)?;
+    }
+    if let Some(pass) = password {
+        put_str(&mut body, "password", pass)?;
+    }
+    Ok(packet(CONNECT, &body))
+}
+
+async fn read_packet(stream: &mut Box<dyn Io>) -> Result<(u8, Vec<u8>), String> {
+    let kind = stream.read_u8().await.map_err(|e| format!("Connection Closed: {}", e))?;
+    let mut len = 0usize;
+    for shift in 0..4 {
+        let byte = stream.read_u8().await.map_err(|e| format!("Connection Closed: {}", e))?;
+        len |= ((byte & 0x7F) as usize) << (7 * shift);
+        if byte & 0x80 == 0 {
+            let mut body = vec![0u8; len];
+            stream.read_exact(&mut body).await.map_err(|e| format!("Truncated Packet: {}", e))?;
+            return Ok((kind, body));
+        }
+    }
+    Err("Malformed Remaining Length".into())
+}
+
+async fn send(stream: &mut Box<dyn Io>, data: &[u8]) -> Result<(), String> {
+    stream.write_all(data).await.map_err(|e| format!("Write Failed: {}", e))?;
+    stream.flush().await.map_err(|e| format!("Write Failed: {}", e))
+}
+
+/// Subscribes to `topic`, publishes a nonce on it and waits for the broker to deliver it back.
+async fn round_trip(stream: &mut Box<dyn Io>, topic: &str) -> Result<f64, String> {
+    let mut body = 1u16.to_be_bytes().to_vec();
+    put_str(&mut body, "topic", topic)?;
+    body.push(0); // QoS 0
+    send(stream, &packet(SUBSCRIBE, &body)).await?;
+
+    loop {
+        let (kind, body) = read_packet(stream).await?;
+        if kind & 0xF0 == SUBACK {
+            match body.get(2) {
+                Some(0x80) => return Err(format!("Subscription to '{}' Refused", topic)),
+                Some(_) => break,
+                None => return Err("Malformed SUBACK".into()),
+            }
+        }
+    }
+
+    let nonce = format!("spectra-probe-{:016x}", rand::random::<u64>());
+    let mut body = Vec::new();
+    put_str(&mut body, "topic", topic)?;
+    body.extend_from_slice(nonce.as_bytes());
+    let start = Instant::now();
+    send(stream, &packet(PUBLISH, &body)).await?;
+
+    // Retained or concurrent messages on the topic are skipped until our nonce comes back.
+    loop {
+        let (kind, body) = read_packet(stream).await?;
+        if kind & 0xF0 != PUBLISH || body.len() < 2 {
+            continue;
+        }
+        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let qos = (kind >> 1) & 0x03;
+        let payload_start = 2 + topic_len + if qos > 0 { 2 } else { 0 };
+        if body.get(payload_start..) == Some(nonce.as_bytes()) {
+            return Ok(start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+/// Connects to an MQTT 3.1.1 broker, reports the CONNACK code and optionally measures a publish round trip.
+pub async fn check_mqtt(host: &str, check: &CheckType, bind: &SourceBinding) -> ProbeOutcome {
+    let CheckType::Mqtt { port, tls: use_tls, username, password, topic, timeout_ms, .. } = check else {
+        return ProbeOutcome::new(Status::Unknown, "Not an MQTT check");
+    };
+    let port = port.unwrap_or_else(|| default_mqtt_port(*use_tls));
+    let timeout = Duration::from_millis(*timeout_ms);
+    let client_id = format!("spectra-{:08x}", rand::random::<u32>());
+    let connect = match connect_packet(&client_id, username.as_deref(), password.as_deref()) {
+        Ok(p) => p,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, e),
+    };
+    if let Some(Err(e)) = topic.as_deref().map(|t| str_len("topic", t)) {
+        return ProbeOutcome::new(Status::Unknown, e);
+    }
+    let start = Instant::now();
+
+    let tcp = match tokio::time::timeout(timeout, net::connect_tcp(&net::host_port(host, port), bind)).await {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => return ProbeOutcome::new(Status::Down, format!("Connection Failed: {}", e)),
+        Err(_) => return ProbeOutcome::new(Status::Down, "Connection Timeout"),
+    };
+
+    let mut cert_days = None;
+    let mut stream: Box<dyn Io> = if *use_tls {
+        match tokio::time::timeout(timeout, tls::connect(tcp, host)).await {
+            Ok(Ok(s)) => {
+                cert_days = tls::cert_days_left(&s);
+                Box::new(s)
+            }
+            Ok(Err(e)) => return ProbeOutcome::new(Status::Down, e),
+            Err(_) => return ProbeOutcome::new(Status::Down, "TLS Handshake Timeout"),
+        }
+    } else {
+        Box::new(tcp)
+    };
+
+    let handshake = async {
+        send(&mut stream, &connect).await?;
+        let (kind, body) = read_packet(&mut stream).await?;
+        if kind != CONNACK || body.len() < 2 {
+            return Err(format!("Expected CONNACK, got packet type 0x{:02X}", kind));
+        }
+        Ok(body[1])
+    };
+    let code = match tokio::time::timeout(timeout, handshake).await {
+        Ok(Ok(code)) => code,
+        Ok(Err(e)) => return ProbeOutcome::new(Status::Down, format!("Protocol Error: {}", e)),
+        Err(_) => return ProbeOutcome::new(Status::Down, "CONNACK Timeout"),
+    };
+    let connect_ms = start.elapsed().as_secs_f64() * 1000.0;
+    if code != 0 {
+        let mut outcome = ProbeOutcome::new(Status::Down, format!("CONNACK {}: {}", code, connack_reason(code)))
+            .metric("connack_code", code as f64);
+        outcome.latency_ms = Some(connect_ms);
+        return outcome;
+    }
+
+    let mut outcome = match topic {
+        Some(topic) => match tokio::time::timeout(timeout, round_trip(&mut stream, topic)).await {
+            Ok(Ok(rtt)) => ProbeOutcome::new(Status::Up, format!("CONNACK Accepted, Round Trip {:.1}ms", rtt))
+                .metric("roundtrip_ms", rtt),
+            Ok(Err(e)) => ProbeOutcome::new(Status::Degraded, format!("CONNACK Accepted, {}", e)),
+            Err(_) => ProbeOutcome::new(Status::Degraded, format!("CONNACK Accepted, Probe on '{}' Not Delivered", topic)),
+        },
+        None => ProbeOutcome::new(Status::Up, "CONNACK Accepted"),
+    };
+    let _ = send(&mut stream, &packet(DISCONNECT, &[])).await;
+
+    outcome = outcome.metric("connect_ms", connect_ms).metric("connack_code", 0.0);
+    if let Some(days) = cert_days {
+        outcome = outcome.metric("cert_days_left", days);
+    }
+    outcome.latency_ms = Some(connect_ms);
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn remaining_length_boundaries() {
+        assert_eq!(packet(PUBLISH, &[0; 127])[..2], [PUBLISH, 0x7F]);
+        assert_eq!(packet(PUBLISH, &[0; 128])[..3], [PUBLISH, 0x80, 0x01]);
+        assert_eq!(packet(PUBLISH, &[0; 16383])[..3], [PUBLISH, 0xFF, 0x7F]);
+        assert_eq!(packet(PUBLISH, &[0; 16384])[..4], [PUBLISH, 0x80, 0x80, 0x01]);
+        assert_eq!(packet(PUBLISH, &[0; 16384]).len(), 4 + 16384);
+    }
+
+    #[test]
+    fn connect_flags() {
+        // Fixed header (2), protocol name (6) and level (1) precede the flags.
+        let flags = |user, pass| connect_packet("c", user, pass).unwrap()[9];
+        assert_eq!(flags(None, None), 0x02);
+        assert_eq!(flags(Some("u"), None), 0x82);
+        assert_eq!(flags(Some("u"), Some("p")), 0xC2);
+
+        let packet = connect_packet("c", Some("u"), Some("p")).unwrap();
+        assert_eq!(&packet[2..9], b"\x00\x04MQTT\x04");
+        assert_eq!(&packet[packet.len() - 6..], b"\x00\x01u\x00\x01p");
+        assert!(connect_packet("c", Some(&"u".repeat(65536)), None).unwrap_err().contains("username"));
+    }
+
+    async fn read_from(bytes: &[u8]) -> Result<(u8, Vec<u8>), String> {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        client.write_all(bytes).await.unwrap();
+        drop(client);
+        let mut stream: Box<dyn Io> = Box::new(server);
+        read_packet(&mut stream).await
+    }
+
+    #[tokio::test]
+    async fn read_packet_length_limit() {
+        assert_eq!(read_from(&[CONNACK, 0x02, 0x00, 0x05]).await.unwrap(), (CONNACK, vec![0x00, 0x05]));
+
+        let mut long = vec![PUBLISH, 0x80, 0x80, 0x01];
+        long.extend(vec![7; 16384]);
+        assert_eq!(read_from(&long).await.unwrap().1.len(), 16384);
+
+        assert_eq!(read_from(&[PUBLISH, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]).await.unwrap_err(), "Malformed Remaining Length");
+    }
+
+    /// Answers a single CONNECT with a CONNACK carrying `code`.
+    async fn broker(code: u8) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream: Box<dyn Io> = Box::new(stream);
+            let (kind, _) = read_packet(&mut stream).await.unwrap();
+            assert_eq!(kind, CONNECT);
+            send(&mut stream, &packet(CONNACK, &[0x00, code])).await.unwrap();
+            let _ = read_packet(&mut stream).await;
+        });
+        port
+    }
+
+    fn mqtt_check(port: u16) -> CheckType {
+        serde_json::from_value(serde_json::json!({ "type": "Mqtt", "port": port, "timeout_ms": 2000 })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn connack_codes() {
+        let outcome = check_mqtt("127.0.0.1", &mqtt_check(broker(0).await), &SourceBinding::default()).await;
+        assert_eq!(outcome.health, Status::Up);
+        assert_eq!(outcome.message, "CONNACK Accepted");
+
+        let outcome = check_mqtt("127.0.0.1", &mqtt_check(broker(5).await), &SourceBinding::default()).await;
+        assert_eq!(outcome.health, Status::Down);
+        assert_eq!(outcome.message, "CONNACK 5: Not Authorized");
+        assert_eq!(outcome.metrics["connack_code"], 5.0);
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use base64::Engine;
+use surge_ping::{Client as PingClient, Config as PingConfig, ICMP};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+use crate::config::{ProxyConfig, SourceBinding};
+use crate::engine::Monitor;
+
+/// How a TCP or HTTP probe reaches its target: the local binding and an optional proxy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Route {
+    pub bind: SourceBinding,
+    pub proxy: Option<ProxyConfig>,
+}
+
+/// Which side of a proxied connection failed.
+#[derive(Debug)]
+pub enum ProxyError {
+    Proxy(String),
+    Target(String),
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyError::Proxy(e) => write!(f, "Proxy Failure: {}", e),
+            ProxyError::Target(e) => write!(f, "Target Unreachable via Proxy: {}", e),
+        }
+    }
+}
+
+/// Clients for non-default source bindings, built on first use and reused across cycles.
+#[derive(Default)]
+pub struct BoundClients {
+    // surge-ping tears a client down when any clone of it drops, so clones are shared behind an Arc.
+    ping: Mutex<HashMap<(SourceBinding, bool), Arc<PingClient>>>,
+    http: Mutex<HashMap<Route, reqwest::Client>>,
+}
+
+fn bind_error(bind: &SourceBinding, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("cannot bind to {}: {}", bind, e))
+}
+
+#[cfg(target_os = "linux")]
+fn unsupported_interface(_bind: &SourceBinding) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported_interface(bind: &SourceBinding) -> io::Result<()> {
+    match bind.interface {
+        Some(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "interface binding is only supported on Linux")),
+        None => Ok(()),
+    }
+}
+
+/// Opens a TCP connection to `host:port` leaving from the bound address or device.
+pub async fn connect_tcp(addr: &str, bind: &SourceBinding) -> io::Result<TcpStream> {
+    if bind.is_unbound() {
+        return TcpStream::connect(addr).await;
+    }
+    unsupported_interface(bind)?;
+
+    let mut last_error = None;
+    for target in tokio::net::lookup_host(addr).await? {
+        if bind.address.is_some_and(|a| a.is_ipv4() != target.is_ipv4()) {
+            continue;
+        }
+        let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        if let Some(address) = bind.address {
+            socket.bind(SocketAddr::new(address, 0)).map_err(|e| bind_error(bind, e))?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(interface) = &bind.interface {
+            socket.bind_device(Some(interface.as_bytes())).map_err(|e| bind_error(bind, e))?;
+        }
+        match socket.connect(target).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::new(
+        io::ErrorKind::AddrNotAvailable,
+        format!("no address of {} matches the family of {}", addr, bind),
+    )))
+}
+
+/// `host:port`, bracketing IPv6 literals.
+pub fn host_port(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) }
+}
+
+/// Binds an ephemeral UDP socket on the bound address or device, in the family of `target`.
+pub async fn bind_udp(bind: &SourceBinding, target: IpAddr) -> io::Result<UdpSocket> {
+    unsupported_interface(bind)?;
+    let local = bind.address.unwrap_or(match target {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+    let socket = UdpSocket::bind(SocketAddr::new(local, 0)).await.map_err(|e| bind_error(bind, e))?;
+    #[cfg(target_os = "linux")]
+    if let Some(interface) = &bind.interface {
+        socket.bind_device(Some(interface.as_bytes())).map_err(|e| bind_error(bind, e))?;
+    }
+    Ok(socket)
+}
+
+impl Monitor {
+    /// ICMP client whose socket is bound for `bind`, matching the target's address family.
+    pub(crate) fn bound_ping_client(&self, bind: &SourceBinding, target: IpAddr) -> io::Result<Arc<PingClient>> {
+        let key = (bind.clone(), target.is_ipv6());
+        let mut cache = self.bound_clients.ping.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = cache.get(&key) {
+            return Ok(Arc::clone(client));
+        }
+        unsupported_interface(bind)?;
+
+        let mut config = PingConfig::builder().kind(if target.is_ipv6() { ICMP::V6 } else { ICMP::V4 });
+        if let Some(address) = bind.address {
+            config = config.bind(SocketAddr::new(address, 0));
+        }
+        if let Some(interface) = &bind.interface {
+            config = config.interface(interface);
+        }
+        let client = Arc::new(PingClient::new(&config.build()).map_err(|e| bind_error(bind, e))?);
+        cache.insert(key, Arc::clone(&client));
+        Ok(client)
+    }
+
+    /// HTTP client for `route`; the shared client when the check is unbound and unproxied.
+    pub(crate) fn http_client_for(&self, route: &Route) -> Result<reqwest::Client, String> {
+        if *route == Route::default() {
+            return Ok(self.http_client.clone());
+        }
+        let mut cache = self.bound_clients.http.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = cache.get(route) {
+            return Ok(client.clone());
+        }
+        let client = client_builder(route)?.build().map_err(|e| format!("cannot bind to {}: {}", route.bind, e))?;
+        cache.insert(route.clone(), client.clone());
+        Ok(client)
+    }
+
+    /// Proxy that applies to `host`: the check's own, else the global one, unless `no_proxy` exempts it.
+    pub(crate) async fn proxy_for(&self, check_proxy: Option<&ProxyConfig>, host: &str) -> Option<ProxyConfig> {
+        let proxy = match check_proxy {
+            Some(p) => p.clone(),
+            None => self.config.read().await.proxy.clone()?,
+        };
+        (!proxy.bypasses(host)).then_some(proxy)
+    }
+}
+
+/// Client builder that leaves through `route`, for callers that need extra settings such as a cookie jar.
+pub fn client_builder(route: &Route) -> Result<reqwest::ClientBuilder, String> {
+    let bind = &route.bind;
+    unsupported_interface(bind).map_err(|e| e.to_string())?;
+
+    let mut builder = reqwest::Client::builder().local_address(bind.address);
+    #[cfg(target_os = "linux")]
+    if let Some(interface) = &bind.interface {
+        builder = builder.interface(interface);
+    }
+    if let Some(proxy) = &route.proxy {
+        let endpoint = ProxyEndpoint::parse(proxy).map_err(|e| format!("Invalid proxy: {}", e))?;
+        builder = builder.proxy(reqwest::Proxy::all(endpoint.url).map_err(|e| format!("Invalid proxy: {}", e))?);
+    }
+    Ok(builder)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ProxyKind {
+    HttpConnect,
+    Socks5 { remote_dns: bool },
+}
+
+/// A proxy URL split into what the handshakes need, with credentials merged in.
+struct ProxyEndpoint {
+    kind: ProxyKind,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+    url: reqwest::Url,
+}
+
+impl ProxyEndpoint {
+    fn parse(proxy: &ProxyConfig) -> Result<Self, String> {
+        let mut url = reqwest::Url::parse(&proxy.url).map_err(|e| e.to_string())?;
+        let (kind, default_port) = match url.scheme() {
+            "http" => (ProxyKind::HttpConnect, 8080),
+            "socks5" => (ProxyKind::Socks5 { remote_dns: false }, 1080),
+            "socks5h" => (ProxyKind::Socks5 { remote_dns: true }, 1080),
+            other => return Err(format!("unsupported proxy scheme '{}'", other)),
+        };
+        let host = url.host_str().ok_or("proxy URL has no host")?.trim_start_matches('[').trim_end_matches(']').to_string();
+        let port = url.port().unwrap_or(default_port);
+
+        if let Some(username) = &proxy.username {
+            let _ = url.set_username(username);
+            let _ = url.set_password(proxy.password.as_deref());
+        }
+        let credentials = (!url.username().is_empty()).then(|| {
+            let decode = |s: &str| percent_decode(s);
+            (decode(url.username()), decode(url.password().unwrap_or("")))
+        });
+        Ok(Self { kind, host, port, credentials, url })
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Opens a tunnel to `host:port` through an HTTP CONNECT or SOCKS5 proxy.
+pub async fn connect_via_proxy(proxy: &ProxyConfig, host: &str, port: u16, bind: &SourceBinding, timeout: Duration) -> Result<TcpStream, ProxyError> {
+    let endpoint = ProxyEndpoint::parse(proxy).map_err(|e| ProxyError::Proxy(format!("invalid proxy URL: {}", e)))?;
+    let proxy_addr = host_port(&endpoint.host, endpoint.port);
+
+    let mut stream = match tokio::time::timeout(timeout, connect_tcp(&proxy_addr, bind)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Err(ProxyError::Proxy(format!("cannot reach proxy: {}", e))),
+        Err(_) => return Err(ProxyError::Proxy("proxy connect timeout".into())),
+    };
+
+    let handshake = async {
+        match endpoint.kind {
+            ProxyKind::HttpConnect => http_connect(&mut stream, &endpoint, host, port).await,
+            ProxyKind::Socks5 { remote_dns } => socks5_connect(&mut stream, &endpoint, host, port, remote_dns).await,
+        }
+    };
+    match tokio::time::timeout(timeout, handshake).await {
+        Ok(Ok(())) => Ok(stream),
+        Ok(Err(e)) => Err(e),
+        // The proxy accepted us, so a stalled handshake is almost always the upstream connect.
+        Err(_) => Err(ProxyError::Target("proxy did not open the tunnel in time".into())),
+    }
+}
+
+async fn http_connect(stream: &mut TcpStream, endpoint: &ProxyEndpoint, host: &str, port: u16) -> Result<(), ProxyError> {
+    let authority = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some((user, pass)) = &endpoint.credentials {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await.map_err(|e| ProxyError::Proxy(format!("write failed: {}", e)))?;
+
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(ProxyError::Proxy("oversized CONNECT response".into()));
+        }
+        match stream.read(&mut byte).await {
+            Ok(0) => return Err(ProxyError::Proxy("connection closed during CONNECT".into())),
+            Ok(_) => head.push(byte[0]),
+            Err(e) => return Err(ProxyError::Proxy(format!("read failed: {}", e))),
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    let code = status_line.split_whitespace().nth(1).and_then(|c| c.parse::<u16>().ok())
+        .ok_or_else(|| ProxyError::Proxy(format!("malformed CONNECT response '{}'", status_line)))?;
+    match code {
+        200..=299 => Ok(()),
+        407 => Err(ProxyError::Proxy("authentication required (407)".into())),
+        // Gateway errors are the proxy reporting that the upstream connect failed.
+        502..=504 => Err(ProxyError::Target(status_line.to_string())),
+        _ => Err(ProxyError::Proxy(format!("CONNECT refused: {}", status_line))),
+    }
+}
+
+/// SOCKS5 prefixes names and credentials with a single length byte.
+fn socks5_len(field: &str, value: &str) -> Result<u8, ProxyError> {
+    u8::try_from(value.len()).map_err(|_| ProxyError::Proxy(format!("SOCKS5 {} exceeds 255 bytes", field)))
+}
+
+async fn socks5_connect(stream: &mut TcpStream, endpoint: &ProxyEndpoint, host: &str, port: u16, remote_dns: bool) -> Result<(), ProxyError> {
+    let io = |e: io::Error| ProxyError::Proxy(format!("SOCKS5 I/O error: {}", e));
+
+    let method = if endpoint.credentials.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[0x05, 0x01, method]).await.map_err(io)?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await.map_err(io)?;
+    if choice[0] != 0x05 {
+        return Err(ProxyError::Proxy("not a SOCKS5 proxy".into()));
+    }
+    if choice[1] != method {
+        return Err(ProxyError::Proxy("no acceptable authentication method".into()));
+    }
+
+    if let Some((user, pass)) = &endpoint.credentials {
+        let mut auth = vec![0x01, socks5_len("username", user)?];
+        auth.extend_from_slice(user.as_bytes());
+        auth.push(socks5_len("password", pass)?);
+        auth.extend_from_slice(pass.as_bytes());
+        stream.write_all(&auth).await.map_err(io)?;
+        let mut verdict = [0u8; 2];
+        stream.read_exact(&mut verdict).await.map_err(io)?;
+        if verdict[1] != 0x00 {
+            return Err(ProxyError::Proxy("authentication rejected".into()));
+        }
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    let literal = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok();
+    let target_ip = match literal {
+        Some(ip) => Some(ip),
+        None if remote_dns => None,
+        None => Some(tokio::net::lookup_host((host, port)).await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .map(|a| a.ip())
+            .ok_or_else(|| ProxyError::Target(format!("cannot resolve {}", host)))?),
+    };
+    match target_ip {
+        Some(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Some(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        None => {
+            request.push(0x03);
+            request.push(socks5_len("hostname", host)?);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await.map_err(io)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await.map_err(io)?;
+    let bound_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await.map_err(io)?;
+            len[0] as usize
+        }
+        _ => 0,
+    };
+    let mut rest = vec![0u8; bound_len + 2];
+    if reply[1] == 0x00 {
+        stream.read_exact(&mut rest).await.map_err(io)?;
+    }
+
+    match reply[1] {
+        0x00 => Ok(()),
+        0x01 => Err(ProxyError::Proxy("general SOCKS server failure".into())),
+        0x02 => Err(ProxyError::Proxy("connection not allowed by ruleset".into())),
+        0x03 => Err(ProxyError::Target("network unreachable".into())),
+        0x04 => Err(ProxyError::Target("host unreachable".into())),
+        0x05 => Err(ProxyError::Target("connection refused".into())),
+        0x06 => Err(ProxyError::Target("TTL expired".into())),
+        0x07 => Err(ProxyError::Proxy("command not supported".into())),
+        0x08 => Err(ProxyError::Proxy("address type not supported".into())),
+        code => Err(ProxyError::Proxy(format!("unknown SOCKS reply 0x{:02X}", code))),
+    }
+}
@@ -1,5 +1,6 @@
-use crate::models::{CheckResult, Status};
+use crate::models::{CheckResult, ContentChange, Status};
 use crate::engine::Monitor;
+use crate::engine::trace::format_hops;
 use tracing::{info, error};
 use chrono::Utc;
 
@@ -14,14 +15,22 @@ impl Monitor {
         
         let cfg = self.config.read().await;
         if let Some(topic) = &cfg.ntfy_topic {
-            let priority = if new == Status::Down { "5" } else { "3" };
+            let (priority, tags) = match new {
+                Status::Down => ("5", "warning,computer"),
+                Status::Degraded | Status::Unknown => ("4", "warning"),
+                Status::Flapping => ("4", "warning,repeat"),
+                Status::Up => ("3", "heavy_check_mark"),
+            };
             let title = format!("{} -> {:?}", result.server_name, new);
-            let body = format!("{}: {}", result.check_type, result.message);
+            let mut body = format!("{}: {}", result.check_type, result.message);
+            if !result.trace.is_empty() {
+                body.push_str(&format!("\n\nPath:\n{}", format_hops(&result.trace)));
+            }
             
             let req = self.http_client.post(format!("https://ntfy.sh/{}", topic))
                 .header("Title", title)
                 .header("Priority", priority)
-                .header("Tags", if new == Status::Down { "warning,computer" } else { "heavy_check_mark" })
+                .header("Tags", tags)
                 .header("User-Agent", "SPECTRA-Monitor/3.1.0")
                 .body(body);
 
@@ -45,14 +54,20 @@ impl Monitor {
     }
 
     pub async fn send_discord_webhook(&self, url: &str, result: CheckResult, old: Status, new: Status) {
-        let color = if new == Status::Up { 0x2ECC71 } else { 0xE74C3C };
+        let color = match new {
+            Status::Up => 0x2ECC71,
+            Status::Degraded => 0xE67E22,
+            Status::Down => 0xE74C3C,
+            Status::Unknown => 0x95A5A6,
+            Status::Flapping => 0xF1C40F,
+        };
         let display_addr = if result.target_address.starts_with("HIDDEN-") {
             result.server_name.clone()
         } else {
             result.target_address.clone()
         };
 
-        let fields = vec![
+        let mut fields = vec![
             serde_json::json!({ "name": "Cluster", "value": result.server_name, "inline": true }),
             serde_json::json!({ "name": "Resource", "value": display_addr, "inline": true }),
             serde_json::json!({ "name": "Transition", "value": format!("{:?} \u{2192} {:?}", old, new), "inline": true }),
@@ -62,6 +77,27 @@ impl Monitor {
             serde_json::json!({ "name": "Diagnosis", "value": result.message.to_uppercase(), "inline": false })
         ];
 
+        if !result.metrics.is_empty() {
+            let metrics = result.metrics.iter()
+                .map(|(k, v)| format!("{}: {}", k, format_metric(*v)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            fields.push(serde_json::json!({ "name": "Metrics", "value": metrics, "inline": false }));
+        }
+
+        if !result.trace.is_empty() {
+            // Discord rejects field values over 1024 characters; keep whole lines that fit.
+            let mut path = String::new();
+            for line in format_hops(&result.trace).lines() {
+                if path.len() + line.len() + 1 > 1000 {
+                    break;
+                }
+                path.push_str(line);
+                path.push('\n');
+            }
+            fields.push(serde_json::json!({ "name": "Path", "value": format!("```\n{}```", path), "inline": false }));
+        }
+
         let payload = serde_json::json!({
             "username": "SPECTRA Engine",
             "embeds": [{
@@ -75,6 +111,7 @@ impl Monitor {
         let _ = self.http_client.post(url).json(&payload).send().await;
     }
 
+    #[allow(clippy::collapsible_if)]
     pub async fn dispatch_loss_notification(&self, mut result: CheckResult, loss: f64, threshold: f64, alert: bool) {
         if self.config.read().await.hide_endpoints {
             result.mask_addresses();
@@ -127,6 +164,117 @@ impl Monitor {
         }
     }
 
+    pub async fn dispatch_content_notification(&self, mut result: CheckResult, change: ContentChange) {
+        if self.config.read().await.hide_endpoints {
+            result.mask_addresses();
+        }
+
+        let cfg = self.config.read().await;
+        let event_title = format!("Content Changed: {}", result.server_name);
+        info!("Dispatching content event: {} ({})", event_title, result.check_type);
+
+        if let Some(topic) = &cfg.ntfy_topic {
+            let body = format!("{}: {}\n\n{}", result.check_type, result.message, change.diff);
+            let req = self.http_client.post(format!("https://ntfy.sh/{}", topic))
+                .header("Title", event_title.clone())
+                .header("Priority", "4")
+                .header("Tags", "memo,warning")
+                .body(body);
+            let _ = req.send().await;
+        }
+
+        if let Some(url) = &cfg.webhook_url {
+            if url.contains("discord.com") {
+                let display_addr = if result.target_address.starts_with("HIDDEN-") {
+                    result.server_name.clone()
+                } else {
+                    result.target_address.clone()
+                };
+                let fields = vec![
+                    serde_json::json!({ "name": "Cluster", "value": result.server_name, "inline": true }),
+                    serde_json::json!({ "name": "Resource", "value": display_addr, "inline": true }),
+                    serde_json::json!({ "name": "Protocol", "value": result.check_type, "inline": true }),
+                    serde_json::json!({ "name": "Previous Hash", "value": &change.previous_hash[..12], "inline": true }),
+                    serde_json::json!({ "name": "New Hash", "value": &change.hash[..12], "inline": true }),
+                    serde_json::json!({ "name": "Diff", "value": format!("```diff\n{}```", change.diff), "inline": false })
+                ];
+
+                let payload = serde_json::json!({
+                    "username": "SPECTRA Engine",
+                    "embeds": [{
+                        "title": event_title,
+                        "color": 0x9B59B6,
+                        "fields": fields,
+                        "timestamp": Utc::now().to_rfc3339(),
+                        "footer": { "text": "SPECTRA Infrastructure Intelligence" }
+                    }]
+                });
+                let _ = self.http_client.post(url).json(&payload).send().await;
+            } else {
+                let text = if result.target_address.starts_with("HIDDEN-") {
+                    format!("SPECTRA Alert: content of {} changed", result.server_name)
+                } else {
+                    format!("SPECTRA Alert: content of {} ({}) changed", result.server_name, result.target_address)
+                };
+                let payload = serde_json::json!({ "text": text, "diff": change.diff });
+                let _ = self.http_client.post(url).json(&payload).send().await;
+            }
+        }
+    }
+
+    /// One alert for every child transition held back while `parent` was down.
+    pub async fn dispatch_dependency_summary(&self, parent: String, children: Vec<String>) {
+        const LISTED: usize = 10;
+        let cfg = self.config.read().await;
+        let servers: std::collections::HashSet<&str> = children.iter().filter_map(|c| c.split('/').next()).collect();
+        let mut listed = children.iter().take(LISTED).cloned().collect::<Vec<_>>().join(", ");
+        if children.len() > LISTED {
+            listed.push_str(&format!(" (+{} more)", children.len() - LISTED));
+        }
+        let event_title = format!("Dependency Outage: {}", parent);
+        let summary = format!("{} check(s) on {} server(s) behind {} unreachable: {}", children.len(), servers.len(), parent, listed);
+        info!("Dispatching dependency summary: {} ({} suppressed)", event_title, children.len());
+
+        if let Some(topic) = &cfg.ntfy_topic {
+            let req = self.http_client.post(format!("https://ntfy.sh/{}", topic))
+                .header("Title", event_title.clone())
+                .header("Priority", "4")
+                .header("Tags", "link,warning")
+                .body(summary.clone());
+            let _ = req.send().await;
+        }
+
+        if let Some(url) = &cfg.webhook_url {
+            if url.contains("discord.com") {
+                let fields = vec![
+                    serde_json::json!({ "name": "Parent", "value": parent, "inline": true }),
+                    serde_json::json!({ "name": "Servers", "value": servers.len().to_string(), "inline": true }),
+                    serde_json::json!({ "name": "Checks", "value": children.len().to_string(), "inline": true }),
+                    serde_json::json!({ "name": "Suppressed", "value": listed, "inline": false })
+                ];
+
+                let payload = serde_json::json!({
+                    "username": "SPECTRA Engine",
+                    "embeds": [{
+                        "title": event_title,
+                        "color": 0x95A5A6,
+                        "fields": fields,
+                        "timestamp": Utc::now().to_rfc3339(),
+                        "footer": { "text": "SPECTRA Infrastructure Intelligence" }
+                    }]
+                });
+                let _ = self.http_client.post(url).json(&payload).send().await;
+            } else {
+                let payload = serde_json::json!({
+                    "text": format!("SPECTRA Alert: {}", summary),
+                    "parent": parent,
+                    "suppressed": children
+                });
+                let _ = self.http_client.post(url).json(&payload).send().await;
+            }
+        }
+    }
+
     pub async fn send_generic_webhook(&self, url: &str, result: CheckResult, _old: Status, new: Status) {
         let text = if result.target_address.starts_with("HIDDEN-") {
             format!("SPECTRA Alert: {} is now {:?}", result.server_name, new)
@@ -140,3 +288,11 @@ impl Monitor {
     }
 
 }
+
+fn format_metric(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
@@ -0,0 +1,104 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::config::SourceBinding;
+use crate::engine::net;
+use crate::models::{ProbeOutcome, Status};
+
+/// Seconds between the NTP era (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+fn now_ntp() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64()) + NTP_UNIX_OFFSET
+}
+
+fn read_timestamp(buf: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64;
+    let frac = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as f64;
+    secs + frac / 4_294_967_296.0
+}
+
+fn write_timestamp(buf: &mut [u8], ts: f64) {
+    let secs = ts.trunc() as u32;
+    let frac = (ts.fract() * 4_294_967_296.0) as u32;
+    buf[..4].copy_from_slice(&secs.to_be_bytes());
+    buf[4..8].copy_from_slice(&frac.to_be_bytes());
+}
+
+fn reference_id(stratum: u8, raw: &[u8]) -> String {
+    if stratum <= 1 {
+        // Stratum 0/1 carry an ASCII source or kiss code.
+        raw.iter().take_while(|b| **b != 0).map(|b| *b as char).collect()
+    } else {
+        format!("{}.{}.{}.{}", raw[0], raw[1], raw[2], raw[3])
+    }
+}
+
+/// Sends one SNTP v4 client request and grades the server's offset and stratum.
+pub async fn check_ntp(ip: IpAddr, port: u16, max_offset_ms: f64, max_stratum: u8, timeout_ms: u64, bind: &SourceBinding) -> ProbeOutcome {
+    let socket = match net::bind_udp(bind, ip).await {
+        Ok(s) => s,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Socket Layer Logic Fault: {}", e)),
+    };
+
+    let mut request = [0u8; 48];
+    // LI = 0, VN = 4, Mode = 3 (client).
+    request[0] = 0b00_100_011;
+    let t1 = now_ntp();
+    write_timestamp(&mut request[40..48], t1);
+
+    let mut response = [0u8; 48];
+    let exchange = async {
+        socket.send_to(&request, SocketAddr::new(ip, port)).await?;
+        loop {
+            let (len, from) = socket.recv_from(&mut response).await?;
+            if from.ip() == ip && len >= 48 {
+                return Ok::<_, std::io::Error>(());
+            }
+        }
+    };
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), exchange).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return ProbeOutcome::new(Status::Down, format!("NTP Exchange Failed: {}", e)),
+        Err(_) => return ProbeOutcome::new(Status::Down, "NTP Request Timeout"),
+    }
+    let t4 = now_ntp();
+
+    let leap = response[0] >> 6;
+    let mode = response[0] & 0b111;
+    let stratum = response[1];
+    let refid = reference_id(stratum, &response[12..16]);
+
+    if mode != 4 {
+        return ProbeOutcome::new(Status::Down, format!("Unexpected NTP Mode {}", mode));
+    }
+    if stratum == 0 {
+        return ProbeOutcome::new(Status::Down, format!("Kiss-o'-Death: {}", refid));
+    }
+    // The server must echo our transmit time back as its origin timestamp.
+    if (read_timestamp(&response[24..32]) - t1).abs() > 1e-6 {
+        return ProbeOutcome::new(Status::Down, "NTP Origin Timestamp Mismatch");
+    }
+
+    let t2 = read_timestamp(&response[32..40]);
+    let t3 = read_timestamp(&response[40..48]);
+    let offset_ms = ((t2 - t1) + (t3 - t4)) / 2.0 * 1000.0;
+    let delay_ms = ((t4 - t1) - (t3 - t2)) * 1000.0;
+
+    let (health, verdict) = if leap == LEAP_UNSYNCHRONIZED {
+        (Status::Down, "Server Unsynchronized".to_string())
+    } else if offset_ms.abs() > max_offset_ms {
+        (Status::Down, format!("Offset Exceeds {:.0}ms", max_offset_ms))
+    } else if stratum > max_stratum {
+        (Status::Degraded, format!("Stratum Above {}", max_stratum))
+    } else {
+        (Status::Up, "Clock Synchronized".to_string())
+    };
+
+    let mut outcome = ProbeOutcome::new(health, format!("{} (Offset {:+.2}ms, Stratum {}, Ref {})", verdict, offset_ms, stratum, refid))
+        .metric("offset_ms", offset_ms)
+        .metric("delay_ms", delay_ms)
+        .metric("stratum", stratum as f64);
+    outcome.latency_ms = Some(delay_ms);
+    outcome
+}
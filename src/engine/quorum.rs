@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::config::{CheckType, Server};
+use crate::engine::Monitor;
+use crate::models::{CheckResult, NodeVerdict, Status};
+
+/// Asks a peer to run one check right away and reply under `id`.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfirmRequest {
+    id: String,
+    requester: String,
+    category: String,
+    server: String,
+    address: String,
+    check: String,
+    deadline: DateTime<Utc>,
+}
+
+/// The `count` live nodes following `node_id` in ring order.
+fn pick_peers(live_nodes: &[String], node_id: &str, count: usize) -> Vec<String> {
+    let start = live_nodes.iter().position(|n| n == node_id).map_or(0, |i| i + 1);
+    live_nodes.iter()
+        .cycle()
+        .skip(start)
+        .take(live_nodes.len())
+        .filter(|n| *n != node_id)
+        .take(count)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum Ruling {
+    Confirmed,
+    /// Most of the nodes heard from saw the target working.
+    Rejected,
+    /// Fewer than the required number of peers replied.
+    Undecided,
+}
+
+/// Rules on the verdicts that arrived, the first being this node's own. Peers that stay silent are
+/// not counted either way, so one dead peer can neither confirm nor block a real outage.
+fn rule(verdicts: &[NodeVerdict], min_replies: usize) -> Ruling {
+    if verdicts.len().saturating_sub(1) < min_replies {
+        return Ruling::Undecided;
+    }
+    let failing = verdicts.iter().filter(|v| !v.health.is_operational()).count();
+    if failing * 2 > verdicts.len() { Ruling::Confirmed } else { Ruling::Rejected }
+}
+
+impl Monitor {
+    /// Answers re-check requests from peers until the process exits.
+    pub async fn serve_confirmations(self: Arc<Self>, node_id: String) {
+        let Some(redis) = self.redis.clone() else { return };
+        loop {
+            let raw = match redis.pop_confirm_request(&node_id, 1.0).await {
+                Ok(Some(raw)) => raw,
+                Ok(None) => continue,
+                Err(_) => {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let Ok(request) = serde_json::from_str::<ConfirmRequest>(&raw) else { continue };
+            if request.deadline <= Utc::now() {
+                continue;
+            }
+
+            let this = Arc::clone(&self);
+            let (redis, node_id) = (redis.clone(), node_id.clone());
+            tokio::spawn(async move {
+                let target = {
+                    let cfg = this.config.read().await;
+                    cfg.categories.iter()
+                        .filter(|c| c.name == request.category)
+                        .flat_map(|c| c.servers.iter())
+                        .find(|s| s.name == request.server)
+                        .and_then(|s| s.checks.iter().find(|c| c.name() == request.check).map(|c| (s.clone(), c.clone())))
+                };
+                // A node with a different config abstains rather than voting on a check it does not know.
+                let Some((server, check)) = target else {
+                    warn!("[QUORUM] {} asked about unknown check {}/{}", request.requester, request.server, request.check);
+                    return;
+                };
+                let answer = {
+                    let _permit = this.concurrency_limiter.acquire().await.ok();
+                    let result = this.run_check_with_retry(server, request.address.clone(), check).await;
+                    NodeVerdict::of(&node_id, &result)
+                };
+                info!("[QUORUM] Re-checked {}/{} for {}: {:?}", request.server, request.check, request.requester, answer.health);
+                if let Ok(json) = serde_json::to_string(&answer) {
+                    let _ = redis.push_confirm_reply(&request.id, &json).await;
+                }
+            });
+        }
+    }
+
+    /// Asks up to `count` peers to re-check a failed result. The failure stands once at least `min_replies`
+    /// peers answered and most of the nodes heard from saw it; otherwise the check keeps its previous health.
+    pub async fn confirm_failure(&self, result: &mut CheckResult, server: &Server, address: &str, check: &CheckType, count: usize, min_replies: usize) {
+        let Some(redis) = &self.redis else { return };
+        // Only nodes allowed to run the check can vouch for it.
+        let selector = server.selector(check);
+        let (node_id, live_nodes) = {
+            let state = self.state.lock().await;
+            let eligible = state.live_nodes.iter()
+                .filter(|id| state.live_node_info.get(*id).map_or(selector.is_empty(), |n| n.matches(&selector)))
+                .cloned()
+                .collect::<Vec<_>>();
+            (state.node_id.clone(), eligible)
+        };
+        let peers = pick_peers(&live_nodes, &node_id, count);
+        if peers.is_empty() {
+            return;
+        }
+
+        let timeout = Duration::from_millis(self.config.read().await.confirm_timeout_ms);
+        let deadline = Utc::now() + chrono::Duration::from_std(timeout).unwrap_or_default();
+        let request = ConfirmRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            requester: node_id.clone(),
+            category: result.category.clone(),
+            server: server.name.clone(),
+            address: address.to_string(),
+            check: check.name(),
+            deadline,
+        };
+        let Ok(json) = serde_json::to_string(&request) else { return };
+        let mut asked = 0;
+        for peer in &peers {
+            match redis.push_confirm_request(peer, &json).await {
+                Ok(()) => asked += 1,
+                Err(e) => warn!("[QUORUM] Could not reach {} for {}/{}: {}", peer, server.name, request.check, e),
+            }
+        }
+
+        let mut verdicts = vec![NodeVerdict::of(&node_id, result)];
+        while asked > 0 && verdicts.len() <= asked {
+            let remaining = (deadline - Utc::now()).num_milliseconds();
+            if remaining <= 0 {
+                break;
+            }
+            match redis.pop_confirm_reply(&request.id, remaining as f64 / 1000.0).await {
+                Ok(Some(raw)) => {
+                    if let Ok(v) = serde_json::from_str::<NodeVerdict>(&raw) {
+                        verdicts.push(v);
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let failing = verdicts.iter().filter(|v| !v.health.is_operational()).count();
+        let total = verdicts.len();
+        let missing = peers.len() + 1 - total;
+        let silent = if missing > 0 { format!(", {} no reply", missing) } else { String::new() };
+        if rule(&verdicts, min_replies) == Ruling::Confirmed {
+            result.message = format!("{} (confirmed by {}/{} nodes{})", result.message, failing, total, silent);
+        } else {
+            // Neither alert nor recover on a failure the mesh did not back up.
+            let key = format!("{}-{}-{}-{}", result.server_name, result.parent_address, result.target_address, result.check_type);
+            let previous = self.state.lock().await.transitions.get(&key).and_then(|t| t.confirmed);
+            result.message = format!("Unconfirmed: failing from {}/{} nodes{} - {}", failing, total, silent, result.message);
+            result.set_health(previous.unwrap_or(Status::Up));
+            if let Some(up) = verdicts.iter().find(|v| v.health.is_operational()) {
+                result.latency_ms = up.latency_ms;
+            }
+        }
+        result.verdicts = verdicts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdicts(healths: &[Status]) -> Vec<NodeVerdict> {
+        healths.iter().enumerate()
+            .map(|(i, h)| NodeVerdict { node: format!("n{}", i), health: *h, latency_ms: None, message: String::new() })
+            .collect()
+    }
+
+    #[test]
+    fn quorum_reached() {
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Down, Status::Up]), 1), Ruling::Confirmed);
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Down]), 1), Ruling::Confirmed);
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Unknown, Status::Down]), 2), Ruling::Confirmed);
+    }
+
+    #[test]
+    fn quorum_rejected() {
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Up]), 1), Ruling::Rejected);
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Up, Status::Degraded]), 1), Ruling::Rejected);
+    }
+
+    #[test]
+    fn silent_peers_are_not_counted() {
+        // Two peers asked, one never replied: the other's agreement is enough.
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Down]), 1), Ruling::Confirmed);
+        // Unless more replies are required, in which case the failure is left undecided.
+        assert_eq!(rule(&verdicts(&[Status::Down, Status::Down]), 2), Ruling::Undecided);
+        // No reply at all never confirms a lone local failure.
+        assert_eq!(rule(&verdicts(&[Status::Down]), 1), Ruling::Undecided);
+    }
+
+    #[test]
+    fn ring_order_skips_self() {
+        let nodes: Vec<String> = ["a", "b", "c", "d"].map(String::from).into();
+        assert_eq!(pick_peers(&nodes, "c", 2), ["d", "a"]);
+        assert_eq!(pick_peers(&nodes, "a", 5), ["b", "c", "d"]);
+    }
+}
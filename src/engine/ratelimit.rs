@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// How often idle host buckets are looked for.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// Refill periods a host bucket must sit unused before it is dropped; by then it is full again.
+const IDLE_REFILLS: f64 = 3.0;
+
+/// Refills at `rate` tokens per second and holds at most one second's worth.
+struct TokenBucket {
+    tokens: f64,
+    rate: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(rate: f64, now: Instant) -> Self {
+        Self { tokens: rate.max(1.0), rate, updated: now }
+    }
+
+    fn refill(&mut self, rate: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate.max(1.0));
+        self.rate = rate;
+        self.updated = now;
+    }
+
+    /// Unused for long enough that a fresh full bucket would behave the same.
+    fn idle(&self, now: Instant) -> bool {
+        let refill_secs = self.rate.max(1.0) / self.rate;
+        now.saturating_duration_since(self.updated).as_secs_f64() >= refill_secs * IDLE_REFILLS
+    }
+
+    /// Time until a whole token is available; zero if one is already.
+    fn wait(&self, rate: f64) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / rate)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    global: Option<TokenBucket>,
+    hosts: HashMap<String, TokenBucket>,
+    pruned: Option<Instant>,
+}
+
+impl Buckets {
+    /// Drops buckets of hosts no longer being checked, so removed targets do not pile up.
+    fn prune(&mut self, now: Instant) {
+        if self.pruned.is_some_and(|at| now.saturating_duration_since(at) < PRUNE_INTERVAL) {
+            return;
+        }
+        self.pruned = Some(now);
+        self.hosts.retain(|_, bucket| !bucket.idle(now));
+    }
+}
+
+/// In-process limit on how fast checks start, overall and per target host.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    /// Waits until a check against `host` fits both the global rate and the host's own, then takes
+    /// a token from each. Returns how long the check was held back.
+    pub async fn acquire(&self, host: &str, per_second: f64, per_host: Option<f64>) -> Duration {
+        let per_host = per_host.filter(|r| *r > 0.0);
+        let mut held = Duration::ZERO;
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().await;
+                buckets.prune(now);
+                let Buckets { global, hosts, .. } = &mut *buckets;
+                let mut wait = Duration::ZERO;
+                let global = (per_second > 0.0).then(|| {
+                    let bucket = global.get_or_insert_with(|| TokenBucket::full(per_second, now));
+                    bucket.refill(per_second, now);
+                    wait = wait.max(bucket.wait(per_second));
+                    bucket
+                });
+                let host = per_host.map(|rate| {
+                    let bucket = hosts.entry(host.to_string()).or_insert_with(|| TokenBucket::full(rate, now));
+                    bucket.refill(rate, now);
+                    wait = wait.max(bucket.wait(rate));
+                    bucket
+                });
+                // Tokens are only taken when both limits allow it, so a busy host never eats the global budget.
+                if wait.is_zero() {
+                    if let Some(bucket) = global {
+                        bucket.tokens -= 1.0;
+                    }
+                    if let Some(bucket) = host {
+                        bucket.tokens -= 1.0;
+                    }
+                }
+                wait
+            };
+            if wait.is_zero() {
+                return held;
+            }
+            tokio::time::sleep(wait).await;
+            held += wait;
+        }
+    }
+}
+
+/// Host part of a target address, e.g. `10.0.0.5` for `https://10.0.0.5:8443/health`.
+pub fn target_host(address: &str) -> String {
+    if address.contains("://")
+        && let Some(host) = reqwest::Url::parse(address).ok().and_then(|u| u.host_str().map(str::to_string))
+    {
+        return host.trim_matches(['[', ']']).to_string();
+    }
+    if let Ok(addr) = address.parse::<std::net::SocketAddr>() {
+        return addr.ip().to_string();
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok() => host.to_string(),
+        _ => address.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_up_to_one_second() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(2.0, start);
+        assert_eq!(bucket.tokens, 2.0);
+        bucket.tokens = 0.0;
+        assert_eq!(bucket.wait(2.0), Duration::from_millis(500));
+
+        bucket.refill(2.0, start + Duration::from_millis(250));
+        assert_eq!(bucket.tokens, 0.5);
+        assert_eq!(bucket.wait(2.0), Duration::from_millis(250));
+        bucket.refill(2.0, start + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 2.0);
+        assert_eq!(bucket.wait(2.0), Duration::ZERO);
+
+        // Slow rates still hold one whole token.
+        let bucket = TokenBucket::full(0.5, start);
+        assert_eq!(bucket.tokens, 1.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn both_buckets_must_allow() {
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.acquire("a", 1.0, Some(1.0)).await, Duration::ZERO);
+
+        // "b" has a token but the global bucket is empty; "b" must not be charged while it waits.
+        let held = limiter.acquire("b", 1.0, Some(1.0)).await;
+        assert_eq!(held, Duration::from_secs(1));
+        let buckets = limiter.buckets.lock().await;
+        assert_eq!(buckets.global.as_ref().unwrap().tokens, 0.0);
+        assert_eq!(buckets.hosts["b"].tokens, 0.0);
+        drop(buckets);
+
+        // A host limit holds back only its own host.
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.acquire("a", 100.0, Some(1.0)).await, Duration::ZERO);
+        assert_eq!(limiter.acquire("a", 100.0, Some(1.0)).await, Duration::from_secs(1));
+        assert_eq!(limiter.acquire("c", 100.0, Some(1.0)).await, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_host_buckets_are_pruned() {
+        let limiter = RateLimiter::default();
+        limiter.acquire("busy", 0.0, Some(1.0)).await;
+        limiter.acquire("gone", 0.0, Some(1.0)).await;
+        limiter.acquire("slow", 0.0, Some(0.01)).await;
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        limiter.acquire("busy", 0.0, Some(1.0)).await;
+        tokio::time::advance(Duration::from_secs(31)).await;
+        limiter.acquire("busy", 0.0, Some(1.0)).await;
+
+        // "slow" refills over 100s and has not sat idle for three of those yet.
+        let buckets = limiter.buckets.lock().await;
+        let mut hosts: Vec<&str> = buckets.hosts.keys().map(String::as_str).collect();
+        hosts.sort();
+        assert_eq!(hosts, ["busy", "slow"]);
+    }
+
+    #[test]
+    fn target_hosts() {
+        assert_eq!(target_host("https://10.0.0.5:8443/health"), "10.0.0.5");
+        assert_eq!(target_host("http://[2001:db8::1]:8080/"), "2001:db8::1");
+        assert_eq!(target_host("example.com:5432"), "example.com");
+        assert_eq!(target_host("[2001:db8::1]:53"), "2001:db8::1");
+        assert_eq!(target_host("2001:db8::1"), "2001:db8::1");
+        assert_eq!(target_host("10.0.0.5"), "10.0.0.5");
+        assert_eq!(target_host("db.internal"), "db.internal");
+    }
+}
@@ -0,0 +1,22 @@
+use crate::config::{MonitorConfig, Server};
+use crate::models::{CheckResult, Status};
+
+/// Seconds between engine cycles: the shortest interval any check can be on.
+pub fn tick(cfg: &MonitorConfig) -> u64 {
+    [cfg.interval_when_down, cfg.interval_when_degraded].into_iter()
+        .chain(cfg.servers().flat_map(|s| [s.interval_when_down, s.interval_when_degraded]))
+        .flatten()
+        .fold(cfg.check_interval, u64::min)
+        .max(1)
+}
+
+/// Seconds until a check is due again, picked from its last result: failing targets are
+/// re-checked on `interval_when_down`, degraded ones on `interval_when_degraded`.
+pub fn interval_for(cfg: &MonitorConfig, server: &Server, last: Option<&CheckResult>) -> u64 {
+    let fast = match last {
+        Some(r) if !r.status => server.interval_when_down.or(cfg.interval_when_down),
+        Some(r) if r.health == Status::Degraded => server.interval_when_degraded.or(cfg.interval_when_degraded),
+        _ => None,
+    };
+    fast.unwrap_or(cfg.check_interval)
+}
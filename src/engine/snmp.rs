@@ -0,0 +1,915 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use cfb_mode::cipher::{AsyncStreamCipher, KeyIvInit};
+use hmac::{Hmac, Mac};
+use sha2::Digest;
+use tokio::net::UdpSocket;
+use crate::config::{parse_oid, CheckType, SnmpAuthProtocol, SnmpOid, SnmpPrivProtocol, SnmpV3, SourceBinding};
+use crate::engine::host::{grade_above, worst};
+use crate::engine::{net, Monitor};
+use crate::models::{ProbeOutcome, Status};
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_IP_ADDRESS: u8 = 0x40;
+const TAG_COUNTER32: u8 = 0x41;
+const TAG_GAUGE32: u8 = 0x42;
+const TAG_TIMETICKS: u8 = 0x43;
+const TAG_COUNTER64: u8 = 0x46;
+const TAG_NO_SUCH_OBJECT: u8 = 0x80;
+const TAG_NO_SUCH_INSTANCE: u8 = 0x81;
+const TAG_END_OF_MIB_VIEW: u8 = 0x82;
+const PDU_GET: u8 = 0xA0;
+const PDU_RESPONSE: u8 = 0xA2;
+const PDU_REPORT: u8 = 0xA8;
+
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_PRIV: u8 = 0x02;
+const FLAG_REPORTABLE: u8 = 0x04;
+const USM_SECURITY_MODEL: i64 = 3;
+const MAX_MESSAGE_SIZE: i64 = 65507;
+
+// ---------------------------------------------------------------- BER codec
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn int(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    // Strip redundant sign bytes while keeping the value's sign bit intact.
+    while start < 7 {
+        let (b, next) = (bytes[start], bytes[start + 1]);
+        if (b == 0x00 && next & 0x80 == 0) || (b == 0xFF && next & 0x80 != 0) {
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    tlv(TAG_INTEGER, &bytes[start..])
+}
+
+fn octets(data: &[u8]) -> Vec<u8> {
+    tlv(TAG_OCTET_STRING, data)
+}
+
+fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &parts.concat())
+}
+
+fn encode_oid(oid: &str) -> Result<Vec<u8>, String> {
+    let arcs = parse_oid(oid)?;
+    let mut content = Vec::new();
+    for arc in std::iter::once(arcs[0] * 40 + arcs[1]).chain(arcs[2..].iter().copied()) {
+        let mut chunk = vec![(arc & 0x7F) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push(0x80 | (rest & 0x7F) as u8);
+            rest >>= 7;
+        }
+        chunk.reverse();
+        content.extend(chunk);
+    }
+    Ok(tlv(TAG_OID, &content))
+}
+
+/// Dotted form that `decode_oid` produces for the same OID.
+fn canonical_oid(oid: &str) -> String {
+    parse_oid(oid).map(|arcs| arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")).unwrap_or_default()
+}
+
+fn sample_key(check_key: &str, oid: &str) -> String {
+    format!("{}|{}", check_key, oid)
+}
+
+/// Keys of the counter samples that `check` keeps between polls; anything else is stale.
+pub fn sample_keys(check_key: &str, check: &CheckType) -> Vec<String> {
+    match check {
+        CheckType::Snmp { oids, .. } => oids.iter().filter(|o| o.rate).map(|o| sample_key(check_key, &o.oid)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn decode_oid(content: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for b in content {
+        value = (value << 7) | (b & 0x7F) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(".")
+}
+
+fn decode_int(content: &[u8]) -> i64 {
+    let mut value: i64 = if content.first().is_some_and(|b| b & 0x80 != 0) { -1 } else { 0 };
+    for b in content {
+        value = (value << 8) | *b as i64;
+    }
+    value
+}
+
+fn decode_uint(content: &[u8]) -> u64 {
+    content.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+/// Walks BER elements while tracking absolute offsets into the message.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0, end: buf.len() }
+    }
+
+    fn next(&mut self) -> Result<(u8, usize, usize), String> {
+        let malformed = || "Malformed SNMP Packet".to_string();
+        if self.pos + 2 > self.end {
+            return Err(malformed());
+        }
+        let tag = self.buf[self.pos];
+        let first = self.buf[self.pos + 1] as usize;
+        let mut cursor = self.pos + 2;
+        let len = if first & 0x80 == 0 {
+            first
+        } else {
+            let n = first & 0x7F;
+            if n == 0 || n > 4 || cursor + n > self.end {
+                return Err(malformed());
+            }
+            let len = decode_uint(&self.buf[cursor..cursor + n]) as usize;
+            cursor += n;
+            len
+        };
+        if cursor + len > self.end {
+            return Err(malformed());
+        }
+        self.pos = cursor + len;
+        Ok((tag, cursor, cursor + len))
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<(usize, usize), String> {
+        let (t, start, end) = self.next()?;
+        if t != tag {
+            return Err(format!("Unexpected SNMP tag 0x{:02X}, wanted 0x{:02X}", t, tag));
+        }
+        Ok((start, end))
+    }
+
+    fn child(&self, start: usize, end: usize) -> Reader<'a> {
+        Reader { buf: self.buf, pos: start, end }
+    }
+
+    fn int(&mut self) -> Result<i64, String> {
+        let (s, e) = self.expect(TAG_INTEGER)?;
+        Ok(decode_int(&self.buf[s..e]))
+    }
+
+    fn bytes(&mut self, tag: u8) -> Result<&'a [u8], String> {
+        let (s, e) = self.expect(tag)?;
+        Ok(&self.buf[s..e])
+    }
+}
+
+// ---------------------------------------------------------------- PDUs
+
+#[derive(Debug, Clone)]
+pub enum SnmpValue {
+    Integer(i64),
+    Text(Vec<u8>),
+    Oid(String),
+    IpAddress([u8; 4]),
+    Counter32(u64),
+    Gauge32(u64),
+    TimeTicks(u64),
+    Counter64(u64),
+    Null,
+    Missing(&'static str),
+    Other(u8),
+}
+
+impl SnmpValue {
+    fn decode(tag: u8, content: &[u8]) -> Self {
+        match tag {
+            TAG_INTEGER => SnmpValue::Integer(decode_int(content)),
+            TAG_OCTET_STRING => SnmpValue::Text(content.to_vec()),
+            TAG_OID => SnmpValue::Oid(decode_oid(content)),
+            TAG_IP_ADDRESS if content.len() == 4 => SnmpValue::IpAddress([content[0], content[1], content[2], content[3]]),
+            TAG_COUNTER32 => SnmpValue::Counter32(decode_uint(content)),
+            TAG_GAUGE32 => SnmpValue::Gauge32(decode_uint(content)),
+            TAG_TIMETICKS => SnmpValue::TimeTicks(decode_uint(content)),
+            TAG_COUNTER64 => SnmpValue::Counter64(decode_uint(content)),
+            TAG_NULL => SnmpValue::Null,
+            TAG_NO_SUCH_OBJECT => SnmpValue::Missing("noSuchObject"),
+            TAG_NO_SUCH_INSTANCE => SnmpValue::Missing("noSuchInstance"),
+            TAG_END_OF_MIB_VIEW => SnmpValue::Missing("endOfMibView"),
+            other => SnmpValue::Other(other),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            SnmpValue::Integer(v) => Some(*v as f64),
+            SnmpValue::Counter32(v) | SnmpValue::Gauge32(v) | SnmpValue::TimeTicks(v) | SnmpValue::Counter64(v) => Some(*v as f64),
+            SnmpValue::Text(t) => std::str::from_utf8(t).ok().and_then(|s| s.trim().parse().ok()),
+            _ => None,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            SnmpValue::Integer(v) => v.to_string(),
+            SnmpValue::Text(t) => String::from_utf8_lossy(t).into_owned(),
+            SnmpValue::Oid(o) => o.clone(),
+            SnmpValue::IpAddress(ip) => format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3]),
+            SnmpValue::Counter32(v) | SnmpValue::Gauge32(v) | SnmpValue::TimeTicks(v) | SnmpValue::Counter64(v) => v.to_string(),
+            SnmpValue::Null => "null".into(),
+            SnmpValue::Missing(reason) => (*reason).into(),
+            SnmpValue::Other(tag) => format!("<type 0x{:02X}>", tag),
+        }
+    }
+}
+
+struct Pdu {
+    tag: u8,
+    request_id: i64,
+    error_status: i64,
+    error_index: i64,
+    varbinds: Vec<(String, SnmpValue)>,
+}
+
+fn get_pdu(request_id: i64, oids: &[Vec<u8>]) -> Vec<u8> {
+    let binds: Vec<Vec<u8>> = oids.iter().map(|o| seq(&[o.clone(), tlv(TAG_NULL, &[])])).collect();
+    tlv(PDU_GET, &[int(request_id), int(0), int(0), seq(&binds)].concat())
+}
+
+fn parse_pdu(reader: &mut Reader) -> Result<Pdu, String> {
+    let (tag, start, end) = reader.next()?;
+    let mut pdu = reader.child(start, end);
+    let request_id = pdu.int()?;
+    let error_status = pdu.int()?;
+    let error_index = pdu.int()?;
+    let (bs, be) = pdu.expect(TAG_SEQUENCE)?;
+    let mut binds = pdu.child(bs, be);
+    let mut varbinds = Vec::new();
+    while binds.pos < binds.end {
+        let (vs, ve) = binds.expect(TAG_SEQUENCE)?;
+        let mut bind = binds.child(vs, ve);
+        let oid = decode_oid(bind.bytes(TAG_OID)?);
+        let (vtag, vstart, vend) = bind.next()?;
+        varbinds.push((oid, SnmpValue::decode(vtag, &reader.buf[vstart..vend])));
+    }
+    Ok(Pdu { tag, request_id, error_status, error_index, varbinds })
+}
+
+fn error_status_name(status: i64) -> &'static str {
+    match status {
+        1 => "tooBig",
+        2 => "noSuchName",
+        3 => "badValue",
+        4 => "readOnly",
+        5 => "genErr",
+        6 => "noAccess",
+        16 => "authorizationError",
+        _ => "agentError",
+    }
+}
+
+fn usm_report_reason(oid: &str) -> &'static str {
+    match oid {
+        "1.3.6.1.6.3.15.1.1.1.0" => "Unsupported Security Level",
+        "1.3.6.1.6.3.15.1.1.2.0" => "Not In Time Window",
+        "1.3.6.1.6.3.15.1.1.3.0" => "Unknown User Name",
+        "1.3.6.1.6.3.15.1.1.4.0" => "Unknown Engine ID",
+        "1.3.6.1.6.3.15.1.1.5.0" => "Wrong Digest (Authentication Rejected)",
+        "1.3.6.1.6.3.15.1.1.6.0" => "Decryption Error",
+        _ => "Agent Report",
+    }
+}
+
+async fn exchange(socket: &UdpSocket, target: SocketAddr, request: &[u8]) -> Result<Vec<u8>, String> {
+    socket.send_to(request, target).await.map_err(|e| format!("Datagram Send Failed: {}", e))?;
+    let mut buf = vec![0u8; 65535];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await.map_err(|e| format!("Datagram Receive Failed: {}", e))?;
+        if from.ip() == target.ip() {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+// ---------------------------------------------------------------- v2c
+
+async fn get_v2c(socket: &UdpSocket, target: SocketAddr, community: &str, oids: &[Vec<u8>]) -> Result<Pdu, String> {
+    let request_id = rand::random::<u32>() as i64 & 0x7FFF_FFFF;
+    let message = seq(&[int(1), octets(community.as_bytes()), get_pdu(request_id, oids)]);
+    loop {
+        let response = exchange(socket, target, &message).await?;
+        let mut outer = Reader::new(&response);
+        let (s, e) = outer.expect(TAG_SEQUENCE)?;
+        let mut msg = outer.child(s, e);
+        let _version = msg.int()?;
+        let _community = msg.bytes(TAG_OCTET_STRING)?;
+        let pdu = parse_pdu(&mut msg)?;
+        if pdu.request_id == request_id && pdu.tag == PDU_RESPONSE {
+            return Ok(pdu);
+        }
+    }
+}
+
+/// Increase of a counter since `prev`, allowing for a single Counter32 wrap.
+fn counter_delta(value: &SnmpValue, raw: u64, prev: u64) -> Option<u64> {
+    match (value, raw >= prev) {
+        (_, true) => Some(raw - prev),
+        (SnmpValue::Counter32(_), false) => Some(raw + (1u64 << 32) - prev),
+        // A shrinking 64-bit counter means the agent reset; start over.
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------- v3 / USM
+
+fn password_to_key<D: Digest>(password: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    let mut fed = 0usize;
+    let mut chunk = [0u8; 64];
+    while fed < 1_048_576 {
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b = password[(fed + i) % password.len()];
+        }
+        hasher.update(chunk);
+        fed += 64;
+    }
+    hasher.finalize().to_vec()
+}
+
+fn localize<D: Digest>(key: &[u8], engine_id: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    hasher.update(key);
+    hasher.update(engine_id);
+    hasher.update(key);
+    hasher.finalize().to_vec()
+}
+
+/// RFC 3414 A.2 key localization for the configured auth protocol.
+pub fn localized_key(proto: SnmpAuthProtocol, password: &str, engine_id: &[u8]) -> Vec<u8> {
+    let pw = password.as_bytes();
+    match proto {
+        SnmpAuthProtocol::Md5 => localize::<md5::Md5>(&password_to_key::<md5::Md5>(pw), engine_id),
+        SnmpAuthProtocol::Sha => localize::<sha1::Sha1>(&password_to_key::<sha1::Sha1>(pw), engine_id),
+        SnmpAuthProtocol::Sha256 => localize::<sha2::Sha256>(&password_to_key::<sha2::Sha256>(pw), engine_id),
+    }
+}
+
+fn auth_param_len(proto: SnmpAuthProtocol) -> usize {
+    match proto {
+        SnmpAuthProtocol::Md5 | SnmpAuthProtocol::Sha => 12,
+        SnmpAuthProtocol::Sha256 => 24,
+    }
+}
+
+fn sign(proto: SnmpAuthProtocol, key: &[u8], message: &[u8]) -> Vec<u8> {
+    let digest = match proto {
+        SnmpAuthProtocol::Md5 => {
+            let mut mac = Hmac::<md5::Md5>::new_from_slice(key).expect("HMAC accepts any key length");
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }
+        SnmpAuthProtocol::Sha => {
+            let mut mac = Hmac::<sha1::Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }
+        SnmpAuthProtocol::Sha256 => {
+            let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }
+    };
+    digest[..auth_param_len(proto)].to_vec()
+}
+
+fn aes_iv(boots: i64, time: i64, salt: &[u8]) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[..4].copy_from_slice(&(boots as u32).to_be_bytes());
+    iv[4..8].copy_from_slice(&(time as u32).to_be_bytes());
+    iv[8..].copy_from_slice(&salt[..8]);
+    iv
+}
+
+struct Engine {
+    id: Vec<u8>,
+    boots: i64,
+    time: i64,
+}
+
+struct UsmKeys {
+    auth: Option<(SnmpAuthProtocol, Vec<u8>)>,
+    privacy: Option<Vec<u8>>,
+}
+
+impl UsmKeys {
+    fn flags(&self) -> u8 {
+        let mut flags = FLAG_REPORTABLE;
+        if self.auth.is_some() {
+            flags |= FLAG_AUTH;
+        }
+        if self.privacy.is_some() {
+            flags |= FLAG_PRIV;
+        }
+        flags
+    }
+}
+
+fn build_v3(msg_id: i64, flags: u8, engine: &Engine, user: &str, keys: &UsmKeys, scoped_pdu: Vec<u8>) -> Vec<u8> {
+    let auth_len = keys.auth.as_ref().map_or(0, |(p, _)| auth_param_len(*p));
+    let salt: Vec<u8> = if keys.privacy.is_some() { rand::random::<u64>().to_be_bytes().to_vec() } else { Vec::new() };
+
+    let msg_data = match &keys.privacy {
+        Some(key) => {
+            let mut buf = scoped_pdu;
+            cfb_mode::Encryptor::<aes::Aes128>::new(key[..16].into(), &aes_iv(engine.boots, engine.time, &salt).into())
+                .encrypt(&mut buf);
+            octets(&buf)
+        }
+        None => scoped_pdu,
+    };
+
+    let prefix = [octets(&engine.id), int(engine.boots), int(engine.time), octets(user.as_bytes())].concat();
+    let auth_tlv = octets(&vec![0u8; auth_len]);
+    let sec_content = [prefix.clone(), auth_tlv.clone(), octets(&salt)].concat();
+    let sec = tlv(TAG_SEQUENCE, &sec_content);
+    let auth_in_sec = (sec.len() - sec_content.len()) + prefix.len() + (auth_tlv.len() - auth_len);
+
+    let version = int(3);
+    let header = seq(&[int(msg_id), int(MAX_MESSAGE_SIZE), octets(&[flags]), int(USM_SECURITY_MODEL)]);
+    let sec_octets = octets(&sec);
+    let content = [version.clone(), header.clone(), sec_octets.clone(), msg_data].concat();
+    let mut message = tlv(TAG_SEQUENCE, &content);
+
+    if let Some((proto, key)) = &keys.auth {
+        let sec_pos = (message.len() - content.len()) + version.len() + header.len() + (sec_octets.len() - sec.len());
+        let auth_pos = sec_pos + auth_in_sec;
+        let digest = sign(*proto, key, &message);
+        message[auth_pos..auth_pos + auth_len].copy_from_slice(&digest);
+    }
+    message
+}
+
+struct V3Response {
+    engine: Engine,
+    pdu: Pdu,
+}
+
+fn parse_v3(response: &[u8], keys: Option<&UsmKeys>) -> Result<(i64, V3Response), String> {
+    let mut outer = Reader::new(response);
+    let (s, e) = outer.expect(TAG_SEQUENCE)?;
+    let mut msg = outer.child(s, e);
+    if msg.int()? != 3 {
+        return Err("Agent replied with a non-v3 message".into());
+    }
+    let (hs, he) = msg.expect(TAG_SEQUENCE)?;
+    let mut header = msg.child(hs, he);
+    let msg_id = header.int()?;
+    let _max_size = header.int()?;
+    let flags = header.bytes(TAG_OCTET_STRING)?.first().copied().unwrap_or(0);
+
+    let (ss, se) = msg.expect(TAG_OCTET_STRING)?;
+    let mut sec_outer = msg.child(ss, se);
+    let (ps, pe) = sec_outer.expect(TAG_SEQUENCE)?;
+    let mut sec = sec_outer.child(ps, pe);
+    let engine_id = sec.bytes(TAG_OCTET_STRING)?.to_vec();
+    let boots = sec.int()?;
+    let time = sec.int()?;
+    let _user = sec.bytes(TAG_OCTET_STRING)?;
+    let (as_, ae) = sec.expect(TAG_OCTET_STRING)?;
+    let salt = sec.bytes(TAG_OCTET_STRING)?.to_vec();
+
+    if flags & FLAG_AUTH != 0
+        && let Some((proto, key)) = keys.and_then(|k| k.auth.as_ref())
+    {
+        let mut zeroed = response.to_vec();
+        zeroed[as_..ae].fill(0);
+        if sign(*proto, key, &zeroed) != response[as_..ae] {
+            return Err("Response Digest Mismatch".into());
+        }
+    }
+
+    let scoped_bytes = if flags & FLAG_PRIV != 0 {
+        let key = keys.and_then(|k| k.privacy.as_ref()).ok_or("Encrypted response without privacy key")?;
+        if salt.len() != 8 {
+            return Err("Malformed privacy parameters".into());
+        }
+        let mut buf = msg.bytes(TAG_OCTET_STRING)?.to_vec();
+        cfb_mode::Decryptor::<aes::Aes128>::new(key[..16].into(), &aes_iv(boots, time, &salt).into())
+            .decrypt(&mut buf);
+        buf
+    } else {
+        let (ds, de) = msg.expect(TAG_SEQUENCE)?;
+        tlv(TAG_SEQUENCE, &response[ds..de])
+    };
+
+    let mut scoped_outer = Reader::new(&scoped_bytes);
+    let (cs, ce) = scoped_outer.expect(TAG_SEQUENCE).map_err(|_| "Decryption Error (check priv_password)".to_string())?;
+    let mut scoped = scoped_outer.child(cs, ce);
+    let _context_engine = scoped.bytes(TAG_OCTET_STRING)?;
+    let _context_name = scoped.bytes(TAG_OCTET_STRING)?;
+    let pdu = parse_pdu(&mut scoped)?;
+
+    Ok((msg_id, V3Response { engine: Engine { id: engine_id, boots, time }, pdu }))
+}
+
+impl Monitor {
+    /// Localizes a password for one engine, hashing off the async workers and only once per engine.
+    async fn usm_key(&self, proto: SnmpAuthProtocol, password: &str, engine_id: &[u8]) -> Result<Vec<u8>, String> {
+        let cache_key = (proto, password.to_string(), engine_id.to_vec());
+        if let Some(key) = self.state.lock().await.snmp_keys.get(&cache_key) {
+            return Ok(key.clone());
+        }
+        let (pw, id) = (password.to_string(), engine_id.to_vec());
+        let key = tokio::task::spawn_blocking(move || localized_key(proto, &pw, &id))
+            .await
+            .map_err(|e| format!("Key Derivation Failed: {}", e))?;
+        self.state.lock().await.snmp_keys.insert(cache_key, key.clone());
+        Ok(key)
+    }
+
+    async fn usm_keys(&self, v3: &SnmpV3, engine_id: &[u8]) -> Result<UsmKeys, String> {
+        let auth = match (v3.auth_protocol, v3.auth_password.as_deref()) {
+            (Some(proto), Some(pw)) if !pw.is_empty() => Some((proto, self.usm_key(proto, pw, engine_id).await?)),
+            (Some(_), _) => return Err("SNMPv3 auth_protocol requires auth_password".into()),
+            (None, _) => None,
+        };
+        let privacy = match (v3.priv_protocol, v3.priv_password.as_deref(), &auth) {
+            (Some(SnmpPrivProtocol::Aes), Some(pw), Some((proto, _))) if !pw.is_empty() => {
+                Some(self.usm_key(*proto, pw, engine_id).await?[..16].to_vec())
+            }
+            (Some(_), _, None) => return Err("SNMPv3 privacy requires authentication".into()),
+            (Some(_), _, _) => return Err("SNMPv3 priv_protocol requires priv_password".into()),
+            (None, _, _) => None,
+        };
+        Ok(UsmKeys { auth, privacy })
+    }
+
+    async fn get_v3(&self, socket: &UdpSocket, target: SocketAddr, v3: &SnmpV3, oids: &[Vec<u8>]) -> Result<Pdu, String> {
+        let no_keys = UsmKeys { auth: None, privacy: None };
+
+        // Discovery: an unauthenticated probe makes the agent report its engine ID, boots and time.
+        let discovery_id = rand::random::<u32>() as i64 & 0x7FFF_FFFF;
+        let blank = Engine { id: Vec::new(), boots: 0, time: 0 };
+        let probe_pdu = seq(&[octets(&[]), octets(&[]), get_pdu(discovery_id, &[])]);
+        let probe = build_v3(discovery_id, FLAG_REPORTABLE, &blank, "", &no_keys, probe_pdu);
+        let (_, discovered) = parse_v3(&exchange(socket, target, &probe).await?, None)?;
+        if discovered.engine.id.is_empty() {
+            return Err("Agent did not disclose its engine ID".into());
+        }
+
+        let keys = self.usm_keys(v3, &discovered.engine.id).await?;
+        let mut engine = discovered.engine;
+
+        for _ in 0..2 {
+            let msg_id = rand::random::<u32>() as i64 & 0x7FFF_FFFF;
+            let scoped = seq(&[octets(&engine.id), octets(&[]), get_pdu(msg_id, oids)]);
+            let request = build_v3(msg_id, keys.flags(), &engine, &v3.username, &keys, scoped);
+            let response = exchange(socket, target, &request).await?;
+            let (reply_id, reply) = parse_v3(&response, Some(&keys))?;
+            if reply_id != msg_id {
+                continue;
+            }
+            if reply.pdu.tag == PDU_REPORT {
+                let reason = reply.pdu.varbinds.first().map_or("Agent Report", |(oid, _)| usm_report_reason(oid));
+                if reason == "Not In Time Window" {
+                    engine.boots = reply.engine.boots;
+                    engine.time = reply.engine.time;
+                    continue;
+                }
+                return Err(reason.to_string());
+            }
+            if reply.pdu.tag != PDU_RESPONSE {
+                return Err(format!("Unexpected PDU type 0x{:02X}", reply.pdu.tag));
+            }
+            return Ok(reply.pdu);
+        }
+        Err("Agent Time Window Could Not Be Synchronized".into())
+    }
+}
+
+// ---------------------------------------------------------------- check
+
+impl Monitor {
+    /// GETs the configured OIDs and grades them against expectations and thresholds.
+    pub async fn check_snmp(&self, key: &str, ip: IpAddr, check: &CheckType, bind: &SourceBinding) -> ProbeOutcome {
+        let CheckType::Snmp { port, oids, community, v3, timeout_ms, .. } = check else {
+            return ProbeOutcome::new(Status::Unknown, "Not an SNMP check");
+        };
+        let (port, timeout_ms) = (*port, *timeout_ms);
+        if oids.is_empty() {
+            return ProbeOutcome::new(Status::Unknown, "SNMP check has no OIDs configured");
+        }
+        let encoded: Vec<Vec<u8>> = match oids.iter().map(|o| encode_oid(&o.oid)).collect() {
+            Ok(e) => e,
+            Err(e) => return ProbeOutcome::new(Status::Unknown, e),
+        };
+
+        let socket = match net::bind_udp(bind, ip).await {
+            Ok(s) => s,
+            Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Socket Layer Logic Fault: {}", e)),
+        };
+        let target = SocketAddr::new(ip, port);
+
+        let start = Instant::now();
+        let request = async {
+            match v3 {
+                Some(creds) => self.get_v3(&socket, target, creds, &encoded).await,
+                None => get_v2c(&socket, target, community.as_deref().unwrap_or("public"), &encoded).await,
+            }
+        };
+        let pdu = match tokio::time::timeout(Duration::from_millis(timeout_ms), request).await {
+            Ok(Ok(pdu)) => pdu,
+            Ok(Err(e)) => return ProbeOutcome::new(Status::Down, format!("SNMP Failure: {}", e)),
+            Err(_) => return ProbeOutcome::new(Status::Down, "SNMP Request Timeout"),
+        };
+        let latency = start.elapsed().as_secs_f64() * 1000.0;
+
+        if pdu.error_status != 0 {
+            let culprit = oids.get((pdu.error_index as usize).saturating_sub(1)).map_or("?", |o| o.label());
+            return ProbeOutcome::new(Status::Down, format!("Agent Error: {} ({})", error_status_name(pdu.error_status), culprit));
+        }
+
+        let mut outcome = {
+            let mut state = self.state.lock().await;
+            grade(key, oids, &pdu, &mut state.counter_samples, Instant::now())
+        };
+        outcome.latency_ms = Some(latency);
+        outcome
+    }
+}
+
+/// Grades each configured OID against its varbind; rate OIDs compare against the previous sample in `samples`.
+fn grade(key: &str, oids: &[SnmpOid], pdu: &Pdu, samples: &mut HashMap<String, (u64, Instant)>, now: Instant) -> ProbeOutcome {
+    let mut outcome = ProbeOutcome::new(Status::Up, String::new());
+    let mut summary = Vec::new();
+    let mut faults = Vec::new();
+
+    for spec in oids {
+        let label = spec.label();
+        // Agents may reorder or drop varbinds, so each OID is looked up rather than paired by position.
+        let wanted = canonical_oid(&spec.oid);
+        let Some((_, value)) = pdu.varbinds.iter().find(|(oid, _)| *oid == wanted) else {
+            outcome.health = worst(outcome.health, Status::Unknown);
+            faults.push(format!("{}: not in response", label));
+            continue;
+        };
+        if let SnmpValue::Missing(reason) = value {
+            outcome.health = Status::Down;
+            faults.push(format!("{}: {}", label, reason));
+            continue;
+        }
+
+        let rendered = value.render();
+        if let Some(expected) = &spec.expect
+            && &rendered != expected
+        {
+            outcome.health = Status::Down;
+            faults.push(format!("{} = '{}', expected '{}'", label, rendered, expected));
+            continue;
+        }
+
+        let graded = if spec.rate {
+            let raw = match value {
+                SnmpValue::Counter32(v) | SnmpValue::Counter64(v) => *v,
+                _ => {
+                    faults.push(format!("{} is not a counter", label));
+                    outcome.health = worst(outcome.health, Status::Unknown);
+                    continue;
+                }
+            };
+            outcome = outcome.metric(&format!("{}.counter", label), raw as f64);
+            let sample_key = sample_key(key, &spec.oid);
+            let previous = samples.insert(sample_key, (raw, now));
+            match previous {
+                Some((prev, at)) if now > at => {
+                    counter_delta(value, raw, prev).map(|d| d as f64 / now.duration_since(at).as_secs_f64())
+                }
+                _ => None,
+            }
+        } else {
+            value.as_f64()
+        };
+
+        match graded {
+            Some(v) => {
+                outcome = outcome.metric(label, v);
+                let health = grade_above(v, spec.warn, spec.crit);
+                outcome.health = worst(outcome.health, health);
+                if spec.rate {
+                    summary.push(format!("{}={:.2}/s", label, v));
+                } else {
+                    summary.push(format!("{}={}", label, rendered));
+                }
+                if health != Status::Up {
+                    faults.push(format!("{} {:?}", label, health));
+                }
+            }
+            None if spec.rate => summary.push(format!("{}=baseline", label)),
+            None => summary.push(format!("{}={}", label, rendered)),
+        }
+    }
+
+    outcome.message = if faults.is_empty() {
+        format!("SNMP OK: {}", summary.join(", "))
+    } else {
+        format!("SNMP: {}", faults.join("; "))
+    };
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        s.split_whitespace().map(|b| u8::from_str_radix(b, 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn oid_round_trip() {
+        let encoded = encode_oid("1.3.6.1.2.1.1.3.0").unwrap();
+        assert_eq!(encoded, hex("06 08 2b 06 01 02 01 01 03 00"));
+        assert_eq!(decode_oid(&encoded[2..]), "1.3.6.1.2.1.1.3.0");
+
+        // Arcs above 127 span several base-128 bytes.
+        let encoded = encode_oid(".1.3.6.1.4.1.2021.10.1.3.1").unwrap();
+        assert_eq!(&encoded[7..9], &hex("8f 65")[..]);
+        assert_eq!(decode_oid(&encoded[2..]), "1.3.6.1.4.1.2021.10.1.3.1");
+        assert_eq!(decode_oid(&encode_oid("2.999.1").unwrap()[2..]), "2.999.1");
+
+        assert!(encode_oid("1").is_err());
+        assert!(encode_oid("3.1").is_err());
+        assert!(encode_oid("1.3.x").is_err());
+        // The combined first subidentifier must neither collide nor overflow.
+        assert!(encode_oid("1.40").is_err());
+        assert!(encode_oid("2.18446744073709551615").is_err());
+        assert_eq!(canonical_oid(".1.3.06.1"), "1.3.6.1");
+    }
+
+    fn spec(oid: &str, name: &str) -> SnmpOid {
+        SnmpOid { oid: oid.into(), name: Some(name.into()), expect: None, warn: None, crit: None, rate: false }
+    }
+
+    fn response(request_id: i64, binds: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let binds: Vec<Vec<u8>> = binds.iter().map(|(oid, value)| seq(&[encode_oid(oid).unwrap(), value.clone()])).collect();
+        seq(&[int(1), octets(b"public"), tlv(PDU_RESPONSE, &[int(request_id), int(0), int(0), seq(&binds)].concat())])
+    }
+
+    #[tokio::test]
+    async fn v2c_get_and_counter_rate_over_loopback() {
+        let agent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = agent.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            for counter in [1_000i64, 6_000] {
+                let (len, from) = agent.recv_from(&mut buf).await.unwrap();
+                let mut outer = Reader::new(&buf[..len]);
+                let (s, e) = outer.expect(TAG_SEQUENCE).unwrap();
+                let mut msg = outer.child(s, e);
+                msg.int().unwrap();
+                assert_eq!(msg.bytes(TAG_OCTET_STRING).unwrap(), b"public");
+                let request = parse_pdu(&mut msg).unwrap();
+                assert_eq!(request.tag, PDU_GET);
+                // Answered in the reverse of the requested order.
+                let reply = response(request.request_id, &[
+                    ("1.3.6.1.2.1.1.5.0", octets(b"edge1")),
+                    ("1.3.6.1.2.1.2.2.1.10.1", tlv(TAG_COUNTER32, &int(counter)[2..])),
+                ]);
+                agent.send_to(&reply, from).await.unwrap();
+            }
+        });
+
+        let oids = [
+            SnmpOid { rate: true, ..spec("1.3.6.1.2.1.2.2.1.10.1", "ifIn") },
+            SnmpOid { expect: Some("edge1".into()), ..spec(".1.3.6.1.2.1.1.5.0", "sysName") },
+        ];
+        let encoded: Vec<Vec<u8>> = oids.iter().map(|o| encode_oid(&o.oid).unwrap()).collect();
+        let socket = net::bind_udp(&SourceBinding::default(), target.ip()).await.unwrap();
+        let mut samples = HashMap::new();
+        let t0 = Instant::now();
+
+        let pdu = get_v2c(&socket, target, "public", &encoded).await.unwrap();
+        let first = grade("k", &oids, &pdu, &mut samples, t0);
+        assert_eq!(first.health, Status::Up);
+        assert_eq!(first.message, "SNMP OK: ifIn=baseline, sysName=edge1");
+
+        let pdu = get_v2c(&socket, target, "public", &encoded).await.unwrap();
+        let second = grade("k", &oids, &pdu, &mut samples, t0 + Duration::from_secs(10));
+        assert_eq!(second.health, Status::Up);
+        assert_eq!(second.metrics["ifIn"], 500.0);
+        assert_eq!(second.metrics["ifIn.counter"], 6_000.0);
+        assert_eq!(samples.keys().collect::<Vec<_>>(), [&sample_key("k", "1.3.6.1.2.1.2.2.1.10.1")]);
+    }
+
+    #[test]
+    fn missing_and_exception_varbinds() {
+        let oids = [spec("1.3.6.1.2.1.1.3.0", "uptime"), spec("1.3.6.1.2.1.1.9.9", "absent")];
+        let pdu = |varbinds| Pdu { tag: PDU_RESPONSE, request_id: 1, error_status: 0, error_index: 0, varbinds };
+
+        let outcome = grade("k", &oids, &pdu(vec![("1.3.6.1.2.1.1.3.0".into(), SnmpValue::TimeTicks(42))]), &mut HashMap::new(), Instant::now());
+        assert_eq!(outcome.health, Status::Unknown);
+        assert_eq!(outcome.message, "SNMP: absent: not in response");
+
+        let varbinds = vec![
+            ("1.3.6.1.2.1.1.9.9".into(), SnmpValue::Missing("noSuchInstance")),
+            ("1.3.6.1.2.1.1.3.0".into(), SnmpValue::TimeTicks(42)),
+        ];
+        let outcome = grade("k", &oids, &pdu(varbinds), &mut HashMap::new(), Instant::now());
+        assert_eq!(outcome.health, Status::Down);
+        assert_eq!(outcome.message, "SNMP: absent: noSuchInstance");
+        assert_eq!(outcome.metrics["uptime"], 42.0);
+    }
+
+    #[test]
+    fn integer_round_trip() {
+        assert_eq!(int(0), hex("02 01 00"));
+        assert_eq!(int(127), hex("02 01 7f"));
+        assert_eq!(int(128), hex("02 02 00 80"));
+        assert_eq!(int(-1), hex("02 01 ff"));
+        assert_eq!(int(-129), hex("02 02 ff 7f"));
+        for value in [0, 1, -1, 127, 128, -128, -129, 255, 256, 65535, 0x7FFF_FFFF, i64::MIN, i64::MAX] {
+            assert_eq!(Reader::new(&int(value)).int().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn length_round_trip() {
+        for len in [0, 127, 128, 255, 256, 65535, 70000] {
+            let encoded = octets(&vec![0xAB; len]);
+            let header = match len {
+                0..=127 => 2,
+                128..=255 => 3,
+                256..=65535 => 4,
+                _ => 5,
+            };
+            assert_eq!(encoded.len(), header + len);
+            assert_eq!(Reader::new(&encoded).bytes(TAG_OCTET_STRING).unwrap().len(), len);
+        }
+        assert_eq!(&octets(&[0; 200])[..3], &hex("04 81 c8")[..]);
+        assert_eq!(&octets(&[0; 300])[..4], &hex("04 82 01 2c")[..]);
+        // A length running past the buffer is rejected rather than read.
+        assert!(Reader::new(&hex("04 82 01 2c 00")).next().is_err());
+    }
+
+    #[test]
+    fn rfc3414_key_localization() {
+        let engine_id = hex("00 00 00 00 00 00 00 00 00 00 00 02");
+        assert_eq!(password_to_key::<md5::Md5>(b"maplesyrup"), hex("9f af 32 83 88 4e 92 83 4e bc 98 47 d8 ed d9 63"));
+        assert_eq!(
+            localized_key(SnmpAuthProtocol::Md5, "maplesyrup", &engine_id),
+            hex("52 6f 5e ed 9f cc e2 6f 89 64 c2 93 07 87 d8 2b"),
+        );
+        assert_eq!(
+            password_to_key::<sha1::Sha1>(b"maplesyrup"),
+            hex("9f b5 cc 03 81 49 7b 37 93 52 89 39 ff 78 8d 5d 79 14 52 11"),
+        );
+        assert_eq!(
+            localized_key(SnmpAuthProtocol::Sha, "maplesyrup", &engine_id),
+            hex("66 95 fe bc 92 88 e3 62 82 23 5f c7 15 1f 12 84 97 b3 8f 3f"),
+        );
+    }
+
+    #[test]
+    fn counter32_wraps_and_counter64_resets() {
+        let c32 = SnmpValue::Counter32(0);
+        assert_eq!(counter_delta(&c32, 150, 100), Some(50));
+        assert_eq!(counter_delta(&c32, 10, 0xFFFF_FFF0), Some(26));
+        let c64 = SnmpValue::Counter64(0);
+        assert_eq!(counter_delta(&c64, 10, 0xFFFF_FFF0), None);
+        assert_eq!(counter_delta(&c64, 0x1_0000_0010, 0xFFFF_FFF0), Some(32));
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::{Arc, OnceLock};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+pub fn client_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        Arc::new(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+    }).clone()
+}
+
+/// Wraps an established TCP stream in a verified TLS session.
+pub async fn connect(stream: TcpStream, host: &str) -> Result<TlsStream<TcpStream>, String> {
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| format!("Invalid TLS server name {}: {}", host, e))?;
+    TlsConnector::from(client_config())
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| format!("TLS Handshake Failed: {}", e))
+}
+
+/// Days until the peer's leaf certificate expires.
+pub fn cert_days_left(stream: &TlsStream<TcpStream>) -> Option<f64> {
+    let leaf = stream.get_ref().1.peer_certificates()?.first()?;
+    let (_, cert) = x509_parser::parse_x509_certificate(leaf.as_ref()).ok()?;
+    let not_after = cert.validity().not_after.timestamp();
+    Some((not_after - chrono::Utc::now().timestamp()) as f64 / 86_400.0)
+}
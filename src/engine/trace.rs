@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::net::UdpSocket;
+use crate::engine::Monitor;
+use crate::models::TraceHop;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+/// Routers rate-limit Time Exceeded replies to roughly one per second, so rounds are paced like mtr.
+const ROUND_INTERVAL: Duration = Duration::from_secs(1);
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn echo_request(ident: u16, seq: u16) -> Vec<u8> {
+    let mut packet = vec![0u8; 64];
+    packet[0] = ICMP_ECHO_REQUEST;
+    packet[4..6].copy_from_slice(&ident.to_be_bytes());
+    packet[6..8].copy_from_slice(&seq.to_be_bytes());
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    packet
+}
+
+/// Extracts the probe sequence from an IPv4 datagram answering one of our echoes.
+fn parse_reply(datagram: &[u8], ident: u16) -> Option<(u16, Ipv4Addr, bool)> {
+    let header_len = (*datagram.first()? & 0x0F) as usize * 4;
+    let source = Ipv4Addr::new(*datagram.get(12)?, *datagram.get(13)?, *datagram.get(14)?, *datagram.get(15)?);
+    let icmp = datagram.get(header_len..)?;
+    let echo = match *icmp.first()? {
+        ICMP_ECHO_REPLY => icmp,
+        ICMP_TIME_EXCEEDED | ICMP_UNREACHABLE => {
+            // The error quotes our original IP header followed by the first bytes of the echo.
+            let inner = icmp.get(8..)?;
+            let inner_len = (*inner.first()? & 0x0F) as usize * 4;
+            let quoted = inner.get(inner_len..)?;
+            if *quoted.first()? != ICMP_ECHO_REQUEST {
+                return None;
+            }
+            quoted
+        }
+        _ => return None,
+    };
+    if u16::from_be_bytes([*echo.get(4)?, *echo.get(5)?]) != ident {
+        return None;
+    }
+    let seq = u16::from_be_bytes([*echo.get(6)?, *echo.get(7)?]);
+    Some((seq, source, icmp[0] == ICMP_ECHO_REPLY))
+}
+
+#[derive(Default)]
+struct HopTally {
+    address: Option<Ipv4Addr>,
+    sent: u32,
+    rtts: Vec<f64>,
+    destination: bool,
+}
+
+/// Sends TTL-limited ICMP echoes in rounds and returns per-hop loss and latency up to the target.
+pub async fn trace_path(ip: IpAddr, max_hops: u8, probes: u32, timeout_ms: u64) -> Result<Vec<TraceHop>, String> {
+    let IpAddr::V4(target) = ip else {
+        return Err("Path tracing is only supported for IPv4 targets".into());
+    };
+    let raw = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4))
+        .map_err(|e| format!("Path tracing requires raw ICMP sockets (CAP_NET_RAW): {}", e))?;
+    raw.set_nonblocking(true).map_err(|e| format!("Trace Socket Unavailable: {}", e))?;
+    let socket = UdpSocket::from_std(raw.into()).map_err(|e| format!("Trace Socket Unavailable: {}", e))?;
+
+    let max_hops = max_hops.max(1);
+    // The sequence number packs the round into the high byte and the TTL into the low byte.
+    let probes = probes.clamp(1, 255);
+    let timeout = Duration::from_millis(timeout_ms);
+    let ident: u16 = rand::random();
+    let destination = SocketAddr::new(ip, 0);
+
+    let mut tally: Vec<HopTally> = (0..max_hops).map(|_| HopTally::default()).collect();
+    let mut in_flight: HashMap<u16, Instant> = HashMap::new();
+    let mut path_len = max_hops;
+    let mut round = 0u32;
+    let mut next_send = Instant::now();
+    let mut buf = [0u8; 1500];
+
+    loop {
+        let wake = if round < probes { next_send } else { next_send - ROUND_INTERVAL + timeout };
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let Ok((len, _)) = received else { continue };
+                let Some((seq, source, echo)) = parse_reply(&buf[..len], ident) else { continue };
+                let Some(sent_at) = in_flight.remove(&seq) else { continue };
+                let rtt = sent_at.elapsed();
+                if rtt > timeout {
+                    continue;
+                }
+                let ttl = (seq & 0xFF) as u8;
+                let hop = &mut tally[ttl as usize - 1];
+                hop.address.get_or_insert(source);
+                hop.rtts.push(rtt.as_secs_f64() * 1000.0);
+                if echo || source == target {
+                    hop.destination = true;
+                    path_len = path_len.min(ttl);
+                }
+                if round >= probes && in_flight.keys().all(|seq| (seq & 0xFF) as u8 > path_len) {
+                    break;
+                }
+            }
+            _ = tokio::time::sleep_until(wake.into()) => {
+                if round >= probes {
+                    break;
+                }
+                for ttl in 1..=path_len {
+                    let seq = ((round as u16) << 8) | ttl as u16;
+                    if SockRef::from(&socket).set_ttl_v4(ttl as u32).is_err() {
+                        continue;
+                    }
+                    if socket.send_to(&echo_request(ident, seq), destination).await.is_ok() {
+                        in_flight.insert(seq, Instant::now());
+                        tally[ttl as usize - 1].sent += 1;
+                    }
+                }
+                round += 1;
+                next_send = Instant::now() + ROUND_INTERVAL;
+            }
+        }
+    }
+
+    let mut hops: Vec<TraceHop> = tally.into_iter()
+        .take(path_len as usize)
+        .enumerate()
+        .map(|(i, t)| {
+            let received = t.rtts.len() as u32;
+            TraceHop {
+                ttl: i as u8 + 1,
+                address: t.address.map(|a| a.to_string()),
+                sent: t.sent,
+                received,
+                loss_pct: if t.sent == 0 { 100.0 } else { (t.sent - received) as f64 / t.sent as f64 * 100.0 },
+                avg_ms: (received > 0).then(|| t.rtts.iter().sum::<f64>() / received as f64),
+                worst_ms: t.rtts.iter().copied().reduce(f64::max),
+                destination: t.destination,
+            }
+        })
+        .collect();
+
+    if !hops.last().is_some_and(|h| h.destination) {
+        // Keep one silent hop after the last responder to show where the path dies.
+        let last_seen = hops.iter().rposition(|h| h.address.is_some()).map_or(0, |i| i + 1);
+        hops.truncate((last_seen + 1).min(hops.len()));
+    }
+    Ok(hops)
+}
+
+/// Renders hops as one aligned line each for notifications.
+pub fn format_hops(hops: &[TraceHop]) -> String {
+    hops.iter()
+        .map(|h| format!("{:>2}. {:<15} {:>5.1}% {}",
+            h.ttl,
+            h.address.as_deref().unwrap_or("*"),
+            h.loss_pct,
+            h.avg_ms.map_or("-".to_string(), |l| format!("{:.1}ms", l))))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Monitor {
+    /// Traces the path to a check target using the configured hop limit and timeout.
+    pub async fn trace_target(&self, address: &str, probes: u32) -> Result<Vec<TraceHop>, String> {
+        let ip = self.resolve(address).await.map_err(|e| format!("Domain Resolution Error: {}", e))?;
+        let (max_hops, timeout_ms) = {
+            let cfg = self.config.read().await;
+            (cfg.traceroute.max_hops, cfg.traceroute.timeout_ms)
+        };
+        trace_path(ip, max_hops, probes, timeout_ms).await
+    }
+
+    /// Attaches a finished trace to the latest result for `key` and republishes it.
+    pub async fn store_trace(&self, key: &str, hops: Vec<TraceHop>) {
+        let updated = {
+            let mut state = self.state.lock().await;
+            state.last_results.get_mut(key).map(|r| {
+                r.trace = hops;
+                r.clone()
+            })
+        };
+        if let (Some(redis), Some(result)) = (&self.redis, updated) {
+            let _ = redis.push_result(key, &result).await;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+use futures::{SinkExt, StreamExt};
+use regex::Regex;
+use tokio_tungstenite::tungstenite::Message;
+use crate::config::SourceBinding;
+use crate::engine::net;
+use crate::models::{ProbeOutcome, Status};
+
+/// Completes a WebSocket upgrade and optionally exchanges one message.
+pub async fn check_websocket(url: &str, send: Option<&str>, expect: Option<&str>, timeout_ms: u64, bind: &SourceBinding) -> ProbeOutcome {
+    let pattern = match expect.map(Regex::new).transpose() {
+        Ok(p) => p,
+        Err(e) => return ProbeOutcome::new(Status::Unknown, format!("Invalid expect pattern: {}", e)),
+    };
+    let timeout = Duration::from_millis(timeout_ms);
+
+    let parsed = reqwest::Url::parse(url).ok();
+    let Some((host, port)) = parsed.as_ref().and_then(|u| Some((u.host_str()?, u.port_or_known_default()?))) else {
+        return ProbeOutcome::new(Status::Unknown, format!("Invalid WebSocket URL: {}", url));
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let start = Instant::now();
+    // The TCP leg is opened here so it leaves through the source binding; tungstenite adds TLS for wss.
+    let handshake = async {
+        let tcp = net::connect_tcp(&net::host_port(host, port), bind).await.map_err(|e| format!("Connection Failed: {}", e))?;
+        tokio_tungstenite::client_async_tls(url, tcp).await.map_err(|e| format!("Upgrade Failed: {}", e))
+    };
+    let mut stream = match tokio::time::timeout(timeout, handshake).await {
+        Ok(Ok((stream, _))) => stream,
+        Ok(Err(e)) => return ProbeOutcome::new(Status::Down, e),
+        Err(_) => return ProbeOutcome::new(Status::Down, "Handshake Timeout"),
+    };
+    let handshake_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let mut outcome = ProbeOutcome::new(Status::Up, "WebSocket Upgrade Complete")
+        .metric("handshake_ms", handshake_ms);
+    outcome.latency_ms = Some(handshake_ms);
+
+    if send.is_none() && pattern.is_none() {
+        let _ = stream.close(None).await;
+        return outcome;
+    }
+
+    let exchange_start = Instant::now();
+    if let Some(payload) = send
+        && let Err(e) = stream.send(Message::text(payload)).await
+    {
+        outcome.health = Status::Down;
+        outcome.message = format!("Send Failed: {}", e);
+        return outcome;
+    }
+
+    let remaining = timeout.saturating_sub(start.elapsed());
+    let reply = tokio::time::timeout(remaining, async {
+        while let Some(frame) = stream.next().await {
+            let text = match frame {
+                Ok(Message::Text(t)) => t.to_string(),
+                Ok(Message::Binary(b)) => String::from_utf8_lossy(&b).into_owned(),
+                Ok(Message::Close(_)) => return Err("Connection Closed By Peer".to_string()),
+                Ok(_) => continue,
+                Err(e) => return Err(format!("Receive Failed: {}", e)),
+            };
+            match &pattern {
+                Some(p) if !p.is_match(&text) => continue,
+                _ => return Ok(()),
+            }
+        }
+        Err("Connection Closed By Peer".to_string())
+    }).await;
+
+    match reply {
+        Ok(Ok(())) => {
+            let round_trip_ms = exchange_start.elapsed().as_secs_f64() * 1000.0;
+            outcome = outcome.metric("round_trip_ms", round_trip_ms);
+            outcome.latency_ms = Some(round_trip_ms);
+            outcome.message = "WebSocket Exchange Verified".into();
+        }
+        Ok(Err(e)) => {
+            outcome.health = Status::Down;
+            outcome.message = e;
+        }
+        Err(_) => {
+            outcome.health = Status::Down;
+            outcome.message = match expect {
+                Some(p) => format!("No reply matching '{}'", p),
+                None => "No reply received".into(),
+            };
+        }
+    }
+
+    let _ = stream.close(None).await;
+    outcome
+}
@@ -25,6 +25,14 @@ struct Args {
 
     #[arg(long, env = "NODE_ID")]
     node_id: Option<String>,
+
+    /// Relative share of sharded checks this node takes on.
+    #[arg(long, env = "NODE_WEIGHT", default_value_t = 1.0)]
+    node_weight: f64,
+
+    /// Labels such as `region=eu` that `run_on` selectors match; repeat or comma-separate.
+    #[arg(long = "label", env = "NODE_LABELS", value_delimiter = ',')]
+    labels: Vec<String>,
 }
 
 #[tokio::main]
@@ -44,14 +52,29 @@ async fn main() -> Result<()> {
         .with_context(|| format!("Failed to read baseline config {}", config_path))?;
     let mut config: MonitorConfig = serde_json::from_str(&local_config_content)
         .with_context(|| "Failed to parse baseline config")?;
+    config.validate().map_err(anyhow::Error::msg).with_context(|| "Invalid baseline config")?;
 
     if let Some(p) = args.api_port { config.api_port = p; }
+    if !(args.node_weight.is_finite() && args.node_weight > 0.0) {
+        anyhow::bail!("--node-weight must be a positive number");
+    }
+    let mut labels = std::collections::BTreeMap::new();
+    for label in &args.labels {
+        let Some((k, v)) = label.split_once('=') else {
+            anyhow::bail!("--label expects key=value, got '{}'", label);
+        };
+        labels.insert(k.trim().to_string(), v.trim().to_string());
+    }
 
     let monitor = Arc::new(Monitor::new(config.clone()).await?);
     
-    if let Some(nid) = args.node_id {
+    {
         let mut state = monitor.state.lock().await;
-        state.node_id = nid;
+        if let Some(nid) = args.node_id {
+            state.node_id = nid;
+        }
+        state.node_info.weight = args.node_weight;
+        state.node_info.labels = labels;
     }
 
     let should_run_dashboard = match config.enable_dashboard {
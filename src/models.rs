@@ -1,13 +1,30 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use crate::config::{MaintenanceScope, SnmpAuthProtocol};
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum Status {
+    #[default]
     Up,
+    Degraded,
     Down,
+    Unknown,
+    /// Changing state too often to alert on; `CheckResult::status` still carries the latest probe.
+    Flapping,
+}
+
+impl Status {
+    pub fn from_bool(up: bool) -> Self {
+        if up { Status::Up } else { Status::Down }
+    }
+
+    /// Degraded targets are still serving, so they count as reachable.
+    pub fn is_operational(self) -> bool {
+        matches!(self, Status::Up | Status::Degraded)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,38 +36,231 @@ pub struct CheckResult {
     pub timestamp: DateTime<Utc>,
     pub check_type: String,
     pub status: bool,
+    #[serde(default)]
+    pub health: Status,
     pub latency_ms: Option<f64>,
     pub packet_loss: Option<f64>,
     pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<TraceHop>,
+    /// Set only on the cycle a watched page was seen to change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_change: Option<ContentChange>,
+    /// Top-most down server this check is reached through; its alerts are suppressed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_down: Option<String>,
+    /// Maintenance window or silence covering this result; it is recorded but never alerted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<String>,
+    /// Share of non-maintenance results that were operational, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uptime_pct: Option<f64>,
+    /// Every node's verdict when a failure was put to a quorum, this node first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verdicts: Vec<NodeVerdict>,
+    /// Node that produced this per-location result of an `all_nodes` check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Per-location view behind a combined `all_nodes` result.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<NodeVerdict>,
+    /// Tries made for this result under the retry policy, the first one included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    /// The check passed, but only after at least one failed try.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub succeeded_on_retry: bool,
     pub category_order: usize,
     pub server_order: usize,
     pub check_order: usize,
     pub provider_node: Option<String>,
 }
 
+/// How one node saw a target, for quorum votes and per-location results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeVerdict {
+    pub node: String,
+    pub health: Status,
+    pub latency_ms: Option<f64>,
+    pub message: String,
+}
+
+impl NodeVerdict {
+    pub fn of(node: &str, result: &CheckResult) -> Self {
+        Self {
+            node: node.to_string(),
+            health: result.health,
+            latency_ms: result.latency_ms,
+            message: result.message.clone(),
+        }
+    }
+}
+
+/// One TTL step of a traceroute or MTR run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceHop {
+    pub ttl: u8,
+    /// Responding router, or `None` if every probe at this TTL timed out.
+    pub address: Option<String>,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_pct: f64,
+    pub avg_ms: Option<f64>,
+    pub worst_ms: Option<f64>,
+    /// The reply came from the traced target itself.
+    #[serde(default)]
+    pub destination: bool,
+}
+
+/// A page whose normalized content hashed differently from the last poll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentChange {
+    pub previous_hash: String,
+    pub hash: String,
+    /// Changed lines, `-` removed and `+` added, truncated to a short excerpt.
+    pub diff: String,
+}
+
+/// Last normalized content seen for a `ContentHash` check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentSnapshot {
+    pub hash: String,
+    pub text: String,
+    #[serde(default)]
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+/// An ad-hoc silence created through the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Silence {
+    pub id: String,
+    #[serde(flatten)]
+    pub scope: MaintenanceScope,
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Silence {
+    pub fn describe(&self) -> String {
+        let short = self.id.get(..8).unwrap_or(&self.id);
+        match &self.reason {
+            Some(reason) => format!("silence {}: {}", short, reason),
+            None => format!("silence {}", short),
+        }
+    }
+}
+
+/// Outcome of a probe that grades its own health and carries extra metrics.
+#[derive(Debug, Clone)]
+pub struct ProbeOutcome {
+    pub health: Status,
+    pub latency_ms: Option<f64>,
+    pub message: String,
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl ProbeOutcome {
+    pub fn new(health: Status, message: impl Into<String>) -> Self {
+        Self { health, latency_ms: None, message: message.into(), metrics: BTreeMap::new() }
+    }
 
+    pub fn metric(mut self, name: &str, value: f64) -> Self {
+        self.metrics.insert(name.to_string(), value);
+        self
+    }
+}
 
 impl CheckResult {
+    pub fn new(server_name: &str, parent_address: &str, target_address: &str, check_type: String) -> Self {
+        Self {
+            category: String::new(),
+            server_name: server_name.to_string(),
+            parent_address: parent_address.to_string(),
+            target_address: target_address.to_string(),
+            timestamp: Utc::now(),
+            check_type,
+            status: false,
+            health: Status::Down,
+            latency_ms: None,
+            packet_loss: None,
+            message: String::new(),
+            metrics: BTreeMap::new(),
+            trace: Vec::new(),
+            content_change: None,
+            parent_down: None,
+            maintenance: None,
+            uptime_pct: None,
+            verdicts: Vec::new(),
+            location: None,
+            locations: Vec::new(),
+            attempts: None,
+            succeeded_on_retry: false,
+            category_order: 0,
+            server_order: 0,
+            check_order: 0,
+            provider_node: None,
+        }
+    }
+
+    /// Results cached before `health` existed load it as the default `Up`; take it from `status` instead.
+    pub fn fix_legacy_health(mut self) -> Self {
+        if !self.status && self.health == Status::Up {
+            self.health = Status::Down;
+        }
+        self
+    }
+
+    pub fn set_health(&mut self, health: Status) {
+        self.health = health;
+        self.status = health.is_operational();
+    }
+
+    pub fn apply(&mut self, outcome: ProbeOutcome) {
+        self.set_health(outcome.health);
+        self.latency_ms = outcome.latency_ms;
+        self.message = outcome.message;
+        self.metrics.extend(outcome.metrics);
+    }
+
     pub fn mask_addresses(&mut self) {
 
         let old_target = self.target_address.clone();
         let old_parent = self.parent_address.clone();
 
-        let mut hasher = DefaultHasher::new();
-        self.target_address.hash(&mut hasher);
-        self.target_address = format!("HIDDEN-{}", &format!("{:x}", hasher.finish())[..6].to_uppercase());
-
-        let mut p_hasher = DefaultHasher::new();
-        self.parent_address.hash(&mut p_hasher);
-        self.parent_address = format!("HIDDEN-{}", &format!("{:x}", p_hasher.finish())[..6].to_uppercase());
+        self.target_address = hidden(&self.target_address);
+        self.parent_address = hidden(&self.parent_address);
 
         self.message = self.message.replace(&old_target, &self.target_address);
         if old_parent != old_target {
             self.message = self.message.replace(&old_parent, &self.parent_address);
         }
+
+        for verdict in self.verdicts.iter_mut().chain(self.locations.iter_mut()) {
+            verdict.message = verdict.message.replace(&old_target, &self.target_address);
+        }
+
+        // Routers on the path reveal the network as much as the target does.
+        for hop in self.trace.iter_mut() {
+            if hop.destination {
+                hop.address = Some(self.target_address.clone());
+            } else if let Some(address) = &hop.address {
+                hop.address = Some(hidden(address));
+            }
+        }
     }
 }
 
+/// Stable stand-in for an address, so masked output can still tell addresses apart.
+fn hidden(address: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    address.hash(&mut hasher);
+    format!("HIDDEN-{}", &format!("{:x}", hasher.finish())[..6].to_uppercase())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CategoryStats {
     pub name: String,
@@ -75,7 +285,99 @@ pub struct StatsResponse {
 pub struct MonitorState {
     pub last_results: HashMap<String, CheckResult>,
     pub node_id: String,
+    /// What this node advertises when it registers with the mesh.
+    pub node_info: NodeInfo,
     pub live_nodes: Vec<String>,
+    /// Advertised details of each live node, this one included.
+    pub live_node_info: HashMap<String, NodeInfo>,
     pub last_loss_alerts: HashMap<String, f64>,
+    /// Last raw value and poll time of each SNMP counter polled as a rate.
+    pub counter_samples: HashMap<String, (u64, std::time::Instant)>,
+    /// Localized SNMPv3 keys by protocol, password and engine ID; each costs a megabyte of hashing.
+    pub snmp_keys: HashMap<(SnmpAuthProtocol, String, Vec<u8>), Vec<u8>>,
+    /// When each check key last had an MTR run started.
+    pub last_mtr_runs: HashMap<String, std::time::Instant>,
+    /// Start of the cycle in which each check (or location) key was last scheduled here.
+    pub last_runs: HashMap<String, DateTime<Utc>>,
+    /// Latest normalized page per `ContentHash` check key.
+    pub content_snapshots: HashMap<String, ContentSnapshot>,
+    /// Open handle and read position of each `LogMatch` file.
+    pub log_cursors: HashMap<String, LogCursor>,
+    /// Child transitions held back this cycle, by the parent that was down.
+    pub suppressed_transitions: HashMap<String, Vec<String>>,
+    /// Unexpired silences, refreshed from Redis each cycle in a mesh.
+    pub silences: Vec<Silence>,
+    /// Operational and total result counts per check key, outside maintenance.
+    pub uptime: HashMap<String, (u64, u64)>,
+    pub transitions: HashMap<String, TransitionTracker>,
+}
+
+impl MonitorState {
+    /// A server is down once it has reported and none of its latest results are operational.
+    pub fn server_down(&self, name: &str) -> bool {
+        let mut reported = false;
+        for result in self.last_results.values().filter(|r| r.server_name == name) {
+            if result.status {
+                return false;
+            }
+            reported |= result.message != "Awaiting Infrastructure Handshake...";
+        }
+        reported
+    }
+}
+
+/// Stored as the value of a node's heartbeat key so peers can read it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeInfo {
+    /// Relative share of sharded checks this node takes on.
+    #[serde(default = "default_node_weight")]
+    pub weight: f64,
+    /// Labels such as `region=eu` that `run_on` selectors match against.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn default_node_weight() -> f64 { 1.0 }
+
+impl Default for NodeInfo {
+    fn default() -> Self {
+        Self { weight: default_node_weight(), labels: BTreeMap::new() }
+    }
+}
+
+impl NodeInfo {
+    /// Whether the node carries every label in `selector`.
+    pub fn matches(&self, selector: &BTreeMap<String, String>) -> bool {
+        selector.iter().all(|(k, v)| self.labels.get(k) == Some(v))
+    }
+}
+
+/// Alerting state of one check key: the hysteresis streak and its recent history for flap detection.
+#[derive(Debug, Default)]
+pub struct TransitionTracker {
+    /// Last state that held for long enough, `None` until one has.
+    pub confirmed: Option<Status>,
+    /// Whether `confirmed` was notified; recoveries are only sent for failures that were.
+    pub announced: bool,
+    pub candidate: Option<Status>,
+    pub streak: u32,
+    pub last: Option<Status>,
+    /// Whether each recent result differed from the one before it, oldest first.
+    pub changes: std::collections::VecDeque<bool>,
+    pub flapping: bool,
+}
+
+/// Where a `LogMatch` check left off. The handle stays open so a rotated file can be read to its end.
+#[derive(Debug, Default)]
+pub struct LogCursor {
+    pub file: Option<std::fs::File>,
+    /// Device and inode of the open file, used to notice rotation.
+    pub identity: Option<(u64, u64)>,
+    pub offset: u64,
+    /// Matches found per poll, oldest first, trimmed to the check's window.
+    pub hits: std::collections::VecDeque<(std::time::Instant, u64)>,
+    pub last_match: Option<String>,
+    /// Set once the file has been opened, so later reopenings read new files from the start.
+    pub watching: bool,
 }
 
@@ -1,6 +1,6 @@
 use redis::{AsyncCommands, Client};
 use anyhow::{Result, Context};
-use crate::models::CheckResult;
+use crate::models::{CheckResult, ContentSnapshot, NodeInfo, Silence};
 use std::collections::HashMap;
 use tracing::warn;
 
@@ -17,12 +17,12 @@ impl RedisManager {
         Ok(Self { client, prefix })
     }
 
-    pub async fn register_node(&self, node_id: &str) -> Result<()> {
+    pub async fn register_node(&self, node_id: &str, info: &NodeInfo) -> Result<()> {
         let mut conn = self.client.get_multiplexed_async_connection().await?;
         let key = format!("{}:node:{}", self.prefix, node_id);
         let set_key = format!("{}:nodes", self.prefix);
         
-        let _: () = conn.set_ex(&key, "online", 5).await?;
+        let _: () = conn.set_ex(&key, serde_json::to_string(info)?, 5).await?;
         let _: () = conn.sadd(&set_key, node_id).await?;
         Ok(())
     }
@@ -37,7 +37,8 @@ impl RedisManager {
         Ok(())
     }
 
-    pub async fn cleanup_dead_nodes(&self) -> Result<Vec<String>> {
+    /// Returns the live nodes with what they advertised, sorted by id; older nodes that stored a bare marker get defaults.
+    pub async fn cleanup_dead_nodes(&self) -> Result<Vec<(String, NodeInfo)>> {
         let mut conn = self.client.get_multiplexed_async_connection().await?;
         let set_key = format!("{}:nodes", self.prefix);
         let nodes: Vec<String> = conn.smembers(&set_key).await?;
@@ -45,15 +46,15 @@ impl RedisManager {
         let mut live_nodes = Vec::new();
         for node in nodes {
             let key = format!("{}:node:{}", self.prefix, node);
-            let exists: bool = conn.exists(&key).await?;
-            if exists {
-                live_nodes.push(node);
+            let value: Option<String> = conn.get(&key).await?;
+            if let Some(value) = value {
+                live_nodes.push((node, serde_json::from_str(&value).unwrap_or_default()));
             } else {
                 warn!("Node {} has left the cluster (or timed out). Purging from mesh...", node);
                 let _: () = conn.srem(&set_key, &node).await?;
             }
         }
-        live_nodes.sort();
+        live_nodes.sort_by(|a, b| a.0.cmp(&b.0));
         Ok(live_nodes)
     }
 
@@ -72,8 +73,8 @@ impl RedisManager {
         
         let mut results = HashMap::new();
         for (key, json) in data {
-            if let Ok(res) = serde_json::from_str(&json) {
-                results.insert(key, res);
+            if let Ok(res) = serde_json::from_str::<CheckResult>(&json) {
+                results.insert(key, res.fix_legacy_health());
             }
         }
         Ok(results)
@@ -86,6 +87,100 @@ impl RedisManager {
         Ok(())
     }
 
+    pub async fn fetch_content_snapshot(&self, key: &str) -> Result<Option<ContentSnapshot>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let content_key = format!("{}:content", self.prefix);
+        let json: Option<String> = conn.hget(&content_key, key).await?;
+        Ok(json.and_then(|j| serde_json::from_str(&j).ok()))
+    }
+
+    pub async fn push_content_snapshot(&self, key: &str, snapshot: &ContentSnapshot) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let content_key = format!("{}:content", self.prefix);
+        let json = serde_json::to_string(snapshot)?;
+        let _: () = conn.hset(&content_key, key, json).await?;
+        Ok(())
+    }
+
+    pub async fn push_silence(&self, silence: &Silence) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let silences_key = format!("{}:silences", self.prefix);
+        let json = serde_json::to_string(silence)?;
+        let _: () = conn.hset(&silences_key, &silence.id, json).await?;
+        Ok(())
+    }
+
+    /// Returns unexpired silences, purging the expired ones on the way.
+    pub async fn fetch_silences(&self) -> Result<Vec<Silence>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let silences_key = format!("{}:silences", self.prefix);
+        let data: HashMap<String, String> = conn.hgetall(&silences_key).await?;
+
+        let now = chrono::Utc::now();
+        let mut silences = Vec::new();
+        for (id, json) in data {
+            match serde_json::from_str::<Silence>(&json) {
+                Ok(silence) if silence.expires_at > now => silences.push(silence),
+                _ => { let _: () = conn.hdel(&silences_key, &id).await?; }
+            }
+        }
+        Ok(silences)
+    }
+
+    pub async fn delete_silence(&self, id: &str) -> Result<bool> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let silences_key = format!("{}:silences", self.prefix);
+        let removed: u64 = conn.hdel(&silences_key, id).await?;
+        Ok(removed > 0)
+    }
+
+    /// Counts one result towards a check's uptime, or only reads the counters when `up` is `None`.
+    pub async fn record_uptime(&self, key: &str, up: Option<bool>) -> Result<(u64, u64)> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let uptime_key = format!("{}:uptime", self.prefix);
+        let (up_field, total_field) = (format!("{}|up", key), format!("{}|total", key));
+        if let Some(up) = up {
+            let ups: u64 = conn.hincr(&uptime_key, &up_field, up as u64).await?;
+            let total: u64 = conn.hincr(&uptime_key, &total_field, 1).await?;
+            return Ok((ups, total));
+        }
+        let (ups, total): (Option<u64>, Option<u64>) = redis::cmd("HMGET")
+            .arg(&uptime_key).arg(&up_field).arg(&total_field)
+            .query_async(&mut conn).await?;
+        Ok((ups.unwrap_or(0), total.unwrap_or(0)))
+    }
+
+    /// Queues a re-check request for `node`; requests nobody picks up expire with the queue.
+    pub async fn push_confirm_request(&self, node: &str, request: &str) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let queue_key = format!("{}:confirm:{}", self.prefix, node);
+        let _: () = conn.lpush(&queue_key, request).await?;
+        let _: () = conn.expire(&queue_key, 60).await?;
+        Ok(())
+    }
+
+    pub async fn pop_confirm_request(&self, node: &str, timeout_secs: f64) -> Result<Option<String>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let queue_key = format!("{}:confirm:{}", self.prefix, node);
+        let popped: Option<(String, String)> = conn.brpop(&queue_key, timeout_secs).await?;
+        Ok(popped.map(|(_, request)| request))
+    }
+
+    pub async fn push_confirm_reply(&self, id: &str, reply: &str) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let reply_key = format!("{}:confirm:reply:{}", self.prefix, id);
+        let _: () = conn.lpush(&reply_key, reply).await?;
+        let _: () = conn.expire(&reply_key, 30).await?;
+        Ok(())
+    }
+
+    pub async fn pop_confirm_reply(&self, id: &str, timeout_secs: f64) -> Result<Option<String>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let reply_key = format!("{}:confirm:reply:{}", self.prefix, id);
+        let popped: Option<(String, String)> = conn.blpop(&reply_key, timeout_secs).await?;
+        Ok(popped.map(|(_, reply)| reply))
+    }
+
     pub async fn try_acquire_lock(&self, resource: &str, node_id: &str, ttl_ms: u64) -> bool {
         let mut conn = match self.client.get_multiplexed_async_connection().await {
             Ok(c) => c,